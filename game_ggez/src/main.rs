@@ -10,7 +10,7 @@ use graphics::Text;
 use rand::{Rng, SeedableRng, prelude::SmallRng};
 use raytracer_core::{
     materials::{dielectric::Dielectric, lambertian_diffuse::Lambertian, metal::Metal},
-    shapes::sphere::Sphere,
+    shapes::{plane::Plane, sphere::Sphere},
     Shape,
     GeneratorProgress, PixelRenderer, RandomGenerator, Raytracer, Scene, Vector3,
 };
@@ -111,9 +111,9 @@ impl MyGame {
         }
     }
     fn change_scene(&mut self, seed: u64) {
-        let ground = Sphere::new(
-            Vector3::new(0.0, -100.5, -1.0),
-            100.0,
+        let ground = Plane::new(
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
             Box::new(Lambertian::new_from_hex(0x007070)),
         );
 
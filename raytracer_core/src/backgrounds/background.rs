@@ -0,0 +1,18 @@
+use nalgebra::Vector3;
+
+use crate::shapes::ray::Color;
+
+/// Light coming from infinitely far away, seen by rays that don't touch any shape.
+pub trait Background {
+    fn color(&self, direction: &Vector3<f64>) -> Color;
+
+    /// Probability density of `random_direction` returning `direction`, 0 if the background isn't sampled.
+    fn pdf_value(&self, _direction: &Vector3<f64>) -> f64 {
+        0.0
+    }
+
+    /// Random unit direction toward the bright parts of the background, `None` if it can't be sampled.
+    fn random_direction(&self) -> Option<Vector3<f64>> {
+        None
+    }
+}
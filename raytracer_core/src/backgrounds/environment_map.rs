@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+use std::f64::consts::{PI, TAU};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::hdr::HdrDecoder;
+use image::ImageResult;
+use nalgebra::{Rotation3, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::backgrounds::background::Background;
+use crate::materials::texture_source::luminance;
+use crate::shapes::ray::Color;
+
+/// Piecewise constant distribution over the indices of `weights`.
+struct Distribution {
+    cdf: Vec<f64>,
+    total: f64,
+}
+
+impl Distribution {
+    fn new(weights: impl Iterator<Item = f64>) -> Distribution {
+        let mut total = 0.0;
+        let cdf = weights
+            .map(|weight| {
+                total += weight;
+                total
+            })
+            .collect();
+        Distribution { cdf, total }
+    }
+
+    /// Index whose slice of the cdf contains `u` (between 0 and 1), `None` if every weight is 0.
+    fn sample(&self, u: f64) -> Option<usize> {
+        if self.total <= 0.0 {
+            return None;
+        }
+        let target = u * self.total;
+        let index = self.cdf.partition_point(|&c| c <= target);
+        Some(index.min(self.cdf.len() - 1))
+    }
+
+    fn probability(&self, index: usize) -> f64 {
+        if self.total <= 0.0 {
+            return 0.0;
+        }
+        let previous = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        (self.cdf[index] - previous) / self.total
+    }
+}
+
+/// Equirectangular HDR image surrounding the scene, rays that escape take the color of the pixel
+/// they point to and bright pixels are sampled directly as lights.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    rotation: Rotation3<f64>,
+    rows: Distribution,
+    columns: Vec<Distribution>,
+    rng: RefCell<SmallRng>,
+}
+
+impl EnvironmentMap {
+    /// Loads a Radiance `.hdr` file, `rotation` turns the image around the vertical axis (in degrees)
+    /// and `intensity` scales its radiance.
+    pub fn load_from_file(
+        path: &Path,
+        rotation: f64,
+        intensity: f64,
+    ) -> ImageResult<EnvironmentMap> {
+        let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .iter()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64) * intensity)
+            .collect();
+        Ok(EnvironmentMap::new(
+            metadata.width as usize,
+            metadata.height as usize,
+            pixels,
+            rotation,
+        ))
+    }
+
+    /// `pixels` are stored row by row, from the top of the sky to the bottom.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>, rotation: f64) -> EnvironmentMap {
+        assert_eq!(pixels.len(), width * height);
+        // Rows near the poles are squeezed into a smaller solid angle.
+        let weight = |x: usize, y: usize| {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            luminance(&pixels[y * width + x]) * sin_theta
+        };
+        let columns: Vec<Distribution> = (0..height)
+            .map(|y| Distribution::new((0..width).map(|x| weight(x, y))))
+            .collect();
+        let rows = Distribution::new(columns.iter().map(|row| row.total));
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: Rotation3::from_axis_angle(&Vector3::y_axis(), rotation.to_radians()),
+            rows,
+            columns,
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// Image coordinates between 0 and 1 seen in `direction`.
+    fn direction_to_uv(&self, direction: &Vector3<f64>) -> (f64, f64) {
+        let local = self
+            .rotation
+            .inverse_transform_vector(&direction.normalize());
+        let phi = local.z.atan2(local.x);
+        let theta = local.y.clamp(-1.0, 1.0).acos();
+        ((phi + PI) / TAU, theta / PI)
+    }
+
+    fn uv_to_pixel(&self, u: f64, v: f64) -> (usize, usize) {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        (x, y)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, direction: &Vector3<f64>) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        let (x, y) = self.uv_to_pixel(u, v);
+        self.pixels[y * self.width + x]
+    }
+
+    fn pdf_value(&self, direction: &Vector3<f64>) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (x, y) = self.uv_to_pixel(u, v);
+        let image_pdf = self.rows.probability(y)
+            * self.columns[y].probability(x)
+            * (self.width * self.height) as f64;
+        // Change of variables from the unit square to the sphere.
+        image_pdf / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn random_direction(&self) -> Option<Vector3<f64>> {
+        let mut rng = self.rng.borrow_mut();
+        let y = self.rows.sample(rng.gen())?;
+        let x = self.columns[y].sample(rng.gen())?;
+        let u = (x as f64 + rng.gen::<f64>()) / self.width as f64;
+        let v = (y as f64 + rng.gen::<f64>()) / self.height as f64;
+
+        let phi = u * TAU - PI;
+        let theta = v * PI;
+        let local = Vector3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        );
+        Some(self.rotation * local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small map with black pixels, turned so that directions don't line up with the pixels.
+    fn map() -> EnvironmentMap {
+        let (width, height) = (8, 4);
+        let pixels = (0..width * height)
+            .map(|i| Color::repeat((i * 5 % 7) as f64))
+            .collect();
+        EnvironmentMap::new(width, height, pixels, 30.0)
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let map = map();
+        // Midpoint rule over the sphere in spherical coordinates.
+        let steps = 400;
+        let step = 1.0 / steps as f64;
+        let mut integral = 0.0;
+        for i in 0..steps {
+            for j in 0..steps {
+                let phi = TAU * (i as f64 + 0.5) * step;
+                let theta = PI * (j as f64 + 0.5) * step;
+                let direction = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                integral += map.pdf_value(&direction) * theta.sin() * TAU * PI * step * step;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01, "{}", integral);
+    }
+
+    #[test]
+    fn samples_follow_pdf() {
+        let map = map();
+        // Integral of the luminance over the sphere, each pixel covering a band of latitude.
+        let expected: f64 = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let theta = |y: usize| PI * y as f64 / map.height as f64;
+                let solid_angle = TAU / map.width as f64 * (theta(y).cos() - theta(y + 1).cos());
+                luminance(&map.pixels[y * map.width + x]) * solid_angle
+            })
+            .sum();
+        // Estimated with the samples, only right if they are drawn with the density `pdf_value` gives.
+        let count = 100_000;
+        let estimate: f64 = (0..count)
+            .map(|_| {
+                let direction = map.random_direction().unwrap();
+                let pdf = map.pdf_value(&direction);
+                assert!(pdf > 0.0);
+                luminance(&map.color(&direction)) / pdf
+            })
+            .sum::<f64>()
+            / count as f64;
+        assert!(
+            (estimate - expected).abs() < 0.01 * expected,
+            "{} {}",
+            estimate,
+            expected
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use nalgebra::Vector3;
+
+use crate::backgrounds::background::Background;
+use crate::shapes::ray::Color;
+
+/// Vertical gradient from `bottom` when looking down to `top` when looking up.
+pub struct Gradient {
+    bottom: Color,
+    top: Color,
+}
+
+impl Gradient {
+    pub fn new(bottom: Color, top: Color) -> Gradient {
+        Gradient { bottom, top }
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background for Gradient {
+    fn color(&self, direction: &Vector3<f64>) -> Color {
+        let t = 0.5 * (direction.normalize().y + 1.0);
+
+        self.bottom * (1.0 - t) + self.top * t
+    }
+}
@@ -0,0 +1,5 @@
+pub mod background;
+pub mod environment_map;
+pub mod gradient;
+pub mod sky;
+pub mod uniform;
@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use nalgebra::{Matrix3, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::backgrounds::background::Background;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::Color;
+
+/// Converts the sky luminance, in kcd/m², to the scale of the other lights.
+const SKY_SCALE: f64 = 0.06;
+/// Irradiance of the sun before it crosses the atmosphere, in the same scale as the sky.
+const SUN_IRRADIANCE: f64 = 4.0;
+/// Half the apparent diameter of the sun, in radians.
+const SUN_ANGULAR_RADIUS: f64 = 0.004_65;
+/// Wavelengths of the red, green and blue channels, in micrometers.
+const WAVELENGTHS: [f64; 3] = [0.68, 0.55, 0.44];
+
+/// Coefficients of the Perez sky luminance distribution.
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    /// `theta` is the angle of the view direction from the zenith, `gamma` its angle from the sun.
+    fn evaluate(&self, theta: f64, gamma: f64) -> f64 {
+        // Clamp the view direction slightly above the horizon where the model diverges.
+        let cos_theta = theta.cos().max(0.01);
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Analytic daylight sky from Preetham et al., "A Practical Analytic Model for Daylight".
+///
+/// The sun is drawn as a disk and sampled as a light, so the shadows it casts line up with the sky.
+/// Directions below the horizon see a diffuse ground of color `ground_albedo` lit by the sun and the sky.
+pub struct Sky {
+    to_sun: Vector3<f64>,
+    sun_theta: f64,
+    zenith: Vector3<f64>,
+    perez: [Perez; 3],
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+    ground: Color,
+    rng: RefCell<SmallRng>,
+}
+
+impl Sky {
+    /// `sun_direction` points toward the sun, `turbidity` goes from 2 for a clear sky to about 10 for a hazy one.
+    pub fn new(sun_direction: Vector3<f64>, turbidity: f64, ground_albedo: Color) -> Sky {
+        let to_sun = sun_direction.normalize();
+        let sun_theta = to_sun.y.clamp(-1.0, 1.0).acos();
+        let t = turbidity;
+        let perez = [
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        // Zenith luminance and chromaticity, the model stops at the horizon.
+        let theta_s = sun_theta.min(FRAC_PI_2);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = zenith_chromaticity(
+            &[
+                [0.00166, -0.00375, 0.00209, 0.0],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            ],
+            theta_s,
+            t,
+        );
+        let zenith_y = zenith_chromaticity(
+            &[
+                [0.00275, -0.00610, 0.00317, 0.0],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            ],
+            theta_s,
+            t,
+        );
+
+        let cos_sun_radius = SUN_ANGULAR_RADIUS.cos();
+        let sun_solid_angle = TAU * (1.0 - cos_sun_radius);
+        let sun_radiance = if to_sun.y > 0.0 {
+            sun_transmittance(sun_theta, t) * SUN_IRRADIANCE / sun_solid_angle
+        } else {
+            Color::zeros()
+        };
+
+        let mut sky = Sky {
+            to_sun,
+            sun_theta,
+            zenith: Vector3::new(zenith_luminance.max(0.0), zenith_x, zenith_y),
+            perez,
+            sun_radiance,
+            cos_sun_radius,
+            ground: Color::zeros(),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        };
+        let irradiance = sky.sky_irradiance() + sun_radiance * sun_solid_angle * to_sun.y.max(0.0);
+        sky.ground = ground_albedo.component_mul(&irradiance) / PI;
+        sky
+    }
+
+    /// Radiance of the sky alone, without the sun disk, for a direction above the horizon.
+    fn sky_radiance(&self, direction: &Vector3<f64>) -> Color {
+        let theta = direction.y.clamp(-1.0, 1.0).acos();
+        let gamma = direction.dot(&self.to_sun).clamp(-1.0, 1.0).acos();
+        let [luminance, x, y] = [0, 1, 2].map(|channel| {
+            self.zenith[channel] * self.perez[channel].evaluate(theta, gamma)
+                / self.perez[channel].evaluate(0.0, self.sun_theta)
+        });
+        xyy_to_rgb(luminance * SKY_SCALE, x, y)
+    }
+
+    /// Light received by a horizontal surface from the whole sky.
+    fn sky_irradiance(&self) -> Color {
+        const STEPS: usize = 32;
+        let mut irradiance = Color::zeros();
+        for i in 0..STEPS {
+            let theta = (i as f64 + 0.5) / STEPS as f64 * FRAC_PI_2;
+            for j in 0..STEPS * 4 {
+                let phi = (j as f64 + 0.5) / (STEPS * 4) as f64 * TAU;
+                let direction = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                irradiance += self.sky_radiance(&direction) * theta.cos() * theta.sin();
+            }
+        }
+        irradiance * (FRAC_PI_2 / STEPS as f64) * (TAU / (STEPS * 4) as f64)
+    }
+}
+
+impl Background for Sky {
+    fn color(&self, direction: &Vector3<f64>) -> Color {
+        let direction = direction.normalize();
+        if direction.y < 0.0 {
+            return self.ground;
+        }
+        let sky = self.sky_radiance(&direction);
+        if direction.dot(&self.to_sun) >= self.cos_sun_radius {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    fn pdf_value(&self, direction: &Vector3<f64>) -> f64 {
+        if self.to_sun.y <= 0.0 || direction.normalize().dot(&self.to_sun) < self.cos_sun_radius {
+            return 0.0;
+        }
+        1.0 / (TAU * (1.0 - self.cos_sun_radius))
+    }
+
+    fn random_direction(&self) -> Option<Vector3<f64>> {
+        if self.to_sun.y <= 0.0 {
+            return None;
+        }
+        // Uniform direction in the cone of the sun disk.
+        let mut rng = self.rng.borrow_mut();
+        let z = 1.0 + rng.gen_range(0.0, 1.0) * (self.cos_sun_radius - 1.0);
+        let phi = TAU * rng.gen_range(0.0, 1.0);
+        let sin_theta = (1.0 - z * z).sqrt();
+        let (tangent, bitangent) = orthonormal_basis(&self.to_sun);
+        Some(
+            tangent * (phi.cos() * sin_theta)
+                + bitangent * (phi.sin() * sin_theta)
+                + self.to_sun * z,
+        )
+    }
+}
+
+/// Polynomial in the sun zenith angle and the turbidity, rows are the coefficients of turbidity², turbidity and 1.
+fn zenith_chromaticity(coefficients: &[[f64; 4]; 3], sun_theta: f64, turbidity: f64) -> f64 {
+    let thetas = [sun_theta.powi(3), sun_theta.powi(2), sun_theta, 1.0];
+    let turbidities = [turbidity * turbidity, turbidity, 1.0];
+    turbidities
+        .iter()
+        .zip(coefficients)
+        .map(|(t, row)| {
+            t * row
+                .iter()
+                .zip(&thetas)
+                .map(|(c, theta)| c * theta)
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+/// Fraction of the sun light crossing the atmosphere, from Rayleigh and aerosol scattering.
+fn sun_transmittance(sun_theta: f64, turbidity: f64) -> Color {
+    // Relative optical air mass from Kasten and Young.
+    let air_mass =
+        1.0 / (sun_theta.cos() + 0.50572 * (96.07995 - sun_theta.to_degrees()).powf(-1.6364));
+    let beta = 0.04608 * turbidity - 0.04586;
+    Color::from_iterator(WAVELENGTHS.iter().map(|&lambda| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+        rayleigh * aerosol
+    }))
+}
+
+fn xyy_to_rgb(luminance: f64, x: f64, y: f64) -> Color {
+    if y <= 0.0 {
+        return Color::zeros();
+    }
+    let xyz = Vector3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+    let rgb = Matrix3::new(
+        3.2406, -1.5372, -0.4986, //
+        -0.9689, 1.8758, 0.0415, //
+        0.0557, -0.2040, 1.0570,
+    ) * xyz;
+    rgb.map(|c| c.max(0.0))
+}
@@ -0,0 +1,21 @@
+use nalgebra::Vector3;
+
+use crate::backgrounds::background::Background;
+use crate::shapes::ray::Color;
+
+/// Same color in every direction, black for scenes only lit by their shapes.
+pub struct Uniform {
+    color: Color,
+}
+
+impl Uniform {
+    pub fn new(color: Color) -> Uniform {
+        Uniform { color }
+    }
+}
+
+impl Background for Uniform {
+    fn color(&self, _direction: &Vector3<f64>) -> Color {
+        self.color
+    }
+}
@@ -1,8 +1,10 @@
+use crate::shapes::aabb::AABB;
 use crate::shapes::ray::Ray;
 use nalgebra::Rotation3;
 use nalgebra::Vector3;
 
 const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const VERTICAL_FIELD_OF_VIEW: f64 = 20.0;
 
 pub struct Camera {
     pub origin: Vector3<f64>,
@@ -10,6 +12,8 @@ pub struct Camera {
     lower_left_corner: Vector3<f64>,
     horizontal: Vector3<f64>,
     vertical: Vector3<f64>,
+    // instants the shutter opens and closes, rays are traced at random times in between
+    shutter: (f64, f64),
 }
 
 impl Camera {
@@ -19,9 +23,8 @@ impl Camera {
     pub fn new_lookat(x: f64, y: f64, z: f64, lookat: Vector3<f64>) -> Camera {
         let origin = Vector3::new(x, y, z);
         let vup = Vector3::new(0.0, 1.0, 0.0);
-        let vertical_field_of_view = 20_f64;
 
-        let viewport_height: f64 = 2.0 * vertical_field_of_view.to_radians();
+        let viewport_height: f64 = 2.0 * VERTICAL_FIELD_OF_VIEW.to_radians();
         let viewport_width: f64 = ASPECT_RATIO * viewport_height;
 
         let w = (origin - lookat).normalize();
@@ -37,9 +40,32 @@ impl Camera {
             horizontal,
             vertical,
             lower_left_corner: origin - horizontal / 2.0 - vertical / 2.0 - w,
+            shutter: (0.0, 0.0),
         }
     }
 
+    /// Camera looking at the center of `bounds` from `direction`, far enough for the whole box to be visible.
+    /// `direction` must not be vertical.
+    pub fn new_framing(bounds: &AABB, direction: Vector3<f64>) -> Camera {
+        let center = bounds.centroid();
+        let radius = bounds.extent().magnitude() / 2.0;
+        let half_angle = VERTICAL_FIELD_OF_VIEW.to_radians().atan();
+        let origin = center + direction.normalize() * (radius / half_angle.sin());
+        Self::new_lookat(origin.x, origin.y, origin.z, center)
+    }
+
+    /// Keeps the shutter open from `open` to `close`, shapes moving in the meantime are blurred.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    /// Instant at `fraction` of the time the shutter is open, from 0 to 1.
+    pub fn time_at(&self, fraction: f64) -> f64 {
+        let (open, close) = self.shutter;
+        open + (close - open) * fraction
+    }
+
     pub fn emit_ray_at(&self, offset_x: f64, offset_y: f64) -> Ray {
         Ray::new(
             self.origin.clone_owned(),
@@ -49,6 +75,11 @@ impl Camera {
         )
     }
 
+    /// Angle between the rays of two neighbouring pixels of an image `height` pixels high.
+    pub fn pixel_spread(&self, height: f64) -> f64 {
+        self.vertical.magnitude() / height
+    }
+
     pub fn rotate(&self, rotation: Vector3<f64>) -> Self {
         let mut look_at_offset = self.lookat - self.origin;
         //look_at_offset.y = 0.0;
@@ -60,6 +91,7 @@ impl Camera {
             self.origin.z,
             self.origin + look_at_offset,
         )
+        .with_shutter(self.shutter.0, self.shutter.1)
     }
     pub fn move_camera(&self, dir: Vector3<f64>) -> Self {
         let mut look_at_offset = self.lookat - self.origin;
@@ -75,5 +107,6 @@ impl Camera {
             self.origin.z + real_dir.z,
             self.lookat + real_dir,
         )
+        .with_shutter(self.shutter.0, self.shutter.1)
     }
 }
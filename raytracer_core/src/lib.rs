@@ -3,15 +3,27 @@
 #![feature(associated_type_bounds)]
 #![feature(trait_alias)]
 
+use std::cell::RefCell;
+
 pub use nalgebra::Vector3;
 use rand::seq::SliceRandom;
 
+use crate::backgrounds::background::Background;
+use crate::backgrounds::gradient::Gradient;
 use crate::camera::Camera;
+use crate::lights::light::Light;
+use crate::shapes::aabb::AABB;
+use crate::shapes::constant_medium::ConstantMedium;
 pub use crate::shapes::shape::Shape;
+use crate::world::{SceneCache, World};
 
+pub mod backgrounds;
 pub mod camera;
+pub mod lights;
+pub mod loaders;
 pub mod materials;
 pub mod shapes;
+pub mod world;
 
 #[derive(Debug, Clone)]
 pub struct PixelColor {
@@ -238,7 +250,12 @@ where
     R: rand::Rng + 'static + Send,
 {
     pub camera: Camera,
+    pub lights: Vec<Box<dyn Light>>,
+    pub background: Box<dyn Background>,
+    /// Medium filling the scene up to the farthest surfaces, usually `ConstantMedium::unbounded`.
+    pub fog: Option<ConstantMedium>,
     info: RaytracerInfo<R>,
+    scene_cache: RefCell<Option<SceneCache>>,
 }
 
 const MAX_SIMILAR_SAMPLE_FOR_A_PIXEL: u8 = 3;
@@ -250,11 +267,15 @@ where
     pub fn new(width: f64, height: f64, random: R) -> Self {
         Raytracer {
             camera: Camera::new(-1.8_f64, 1_f64, 2_f64),
+            lights: vec![],
+            background: Box::new(Gradient::default()),
+            fog: None,
             info: RaytracerInfo {
                 width,
                 height,
                 random,
             },
+            scene_cache: RefCell::new(None),
         }
     }
 
@@ -263,7 +284,13 @@ where
         let r = self
             .camera
             .emit_ray_at(x / (self.info.width - 1.0), y / (self.info.height - 1.0));
-        Some(r.find_collision(&scene)?.1)
+        let mut cache = self.scene_cache.borrow_mut();
+        let cache = SceneCache::update(&mut cache, scene);
+        Some(
+            World::new(scene, cache, &self.lights, self.background.as_ref(), None)
+                .find_collision(&r)?
+                .1,
+        )
     }
 
     pub fn generate_pixel<S: PixelRenderer, G: GeneratorProgress>(
@@ -277,14 +304,28 @@ where
         if pixel.status == GenerationStatus::Final {
             return Some(());
         }
+        let cache = SceneCache::update(self.scene_cache.get_mut(), scene);
+        let world = World::new(
+            scene,
+            cache,
+            &self.lights,
+            self.background.as_ref(),
+            self.fog.as_ref(),
+        );
         let mut samples_color = Vector3::new(0.0, 0.0, 0.0);
+        let spread = self.camera.pixel_spread(self.info.height);
         for _s in 0..samples {
             let offset_x =
                 (pos.x as f64 + self.info.random.gen_range(0.0, 1.0)) / (self.info.width - 1.0);
             let offset_y =
                 (pos.y as f64 + self.info.random.gen_range(0.0, 1.0)) / (self.info.height - 1.0);
-            let r = self.camera.emit_ray_at(offset_x, offset_y);
-            samples_color += r.project_ray(&scene);
+            let time = self.camera.time_at(self.info.random.gen_range(0.0, 1.0));
+            let r = self
+                .camera
+                .emit_ray_at(offset_x, offset_y)
+                .with_spread(spread)
+                .with_time(time);
+            samples_color += r.project_ray(&world);
         }
         if let Some(incremental_raw_light) = pixel.incremental_raw_light {
             samples_color += incremental_raw_light;
@@ -324,6 +365,17 @@ pub fn get_index(x: usize, y: usize, width: usize, height: usize) -> Option<usiz
     }
 }
 
+/// Returns the box containing every bounded shape of the scene, `None` if there is none.
+pub fn get_scene_bounds(scene: &[Box<dyn Shape>]) -> Option<AABB> {
+    scene
+        .iter()
+        .filter_map(|shape| shape.bounding_box())
+        .fold(None, |bounds: Option<AABB>, b| match bounds {
+            Some(bounds) => Some(bounds.surrounding(&b)),
+            None => Some(b),
+        })
+}
+
 fn get_random_positions<R>(width: usize, height: usize, rng: &mut R) -> Vec<PixelCachePosition>
 where
     R: rand::Rng + 'static + Send,
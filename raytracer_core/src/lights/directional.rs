@@ -0,0 +1,31 @@
+use nalgebra::Vector3;
+
+use crate::lights::light::{Light, LightSample};
+use crate::shapes::ray::Color;
+
+/// Light coming from infinitely far away along a single direction, like the sun.
+pub struct DirectionalLight {
+    // toward the light, opposite to the direction light travels
+    to_light: Vector3<f64>,
+    intensity: Color,
+}
+
+impl DirectionalLight {
+    /// `direction` is the direction the light travels in.
+    pub fn new(direction: Vector3<f64>, color: Color, intensity: f64) -> DirectionalLight {
+        DirectionalLight {
+            to_light: -direction.normalize(),
+            intensity: color * intensity,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn illuminate(&self, _position: &Vector3<f64>) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.to_light,
+            distance: f64::INFINITY,
+            intensity: self.intensity,
+        })
+    }
+}
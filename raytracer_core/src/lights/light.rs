@@ -0,0 +1,18 @@
+use nalgebra::Vector3;
+
+use crate::shapes::ray::Color;
+
+/// Light reaching a position from an analytic light, before checking for shadows.
+pub struct LightSample {
+    /// Unit vector from the lit position toward the light.
+    pub direction: Vector3<f64>,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: f64,
+    /// Irradiance on a surface facing the light.
+    pub intensity: Color,
+}
+
+/// Light without any shape, so it can only be reached with shadow rays.
+pub trait Light {
+    fn illuminate(&self, position: &Vector3<f64>) -> Option<LightSample>;
+}
@@ -0,0 +1,4 @@
+pub mod directional;
+pub mod light;
+pub mod point;
+pub mod spot;
@@ -0,0 +1,35 @@
+use nalgebra::Vector3;
+
+use crate::lights::light::{Light, LightSample};
+use crate::shapes::ray::Color;
+
+/// Light emitted equally in every direction from a single point.
+pub struct PointLight {
+    position: Vector3<f64>,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Vector3<f64>, color: Color, intensity: f64) -> PointLight {
+        PointLight {
+            position,
+            intensity: color * intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn illuminate(&self, position: &Vector3<f64>) -> Option<LightSample> {
+        let to_light = self.position - position;
+        let distance_squared = to_light.magnitude_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            intensity: self.intensity / distance_squared,
+        })
+    }
+}
@@ -0,0 +1,66 @@
+use nalgebra::Vector3;
+
+use crate::lights::light::{Light, LightSample};
+use crate::shapes::ray::Color;
+
+/// Point light restricted to a cone, fully lit up to `inner_angle` and fading out until `outer_angle`.
+pub struct SpotLight {
+    position: Vector3<f64>,
+    direction: Vector3<f64>,
+    intensity: Color,
+    cos_inner_angle: f64,
+    cos_outer_angle: f64,
+}
+
+impl SpotLight {
+    /// Angles are in radians, from the center of the cone.
+    pub fn new(
+        position: Vector3<f64>,
+        direction: Vector3<f64>,
+        color: Color,
+        intensity: f64,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            intensity: color * intensity,
+            cos_inner_angle: inner_angle.min(outer_angle).cos(),
+            cos_outer_angle: outer_angle.cos(),
+        }
+    }
+
+    fn falloff(&self, cosine: f64) -> f64 {
+        if cosine >= self.cos_inner_angle {
+            return 1.0;
+        }
+        if cosine <= self.cos_outer_angle {
+            return 0.0;
+        }
+        let t = (cosine - self.cos_outer_angle) / (self.cos_inner_angle - self.cos_outer_angle);
+        // smoothstep
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn illuminate(&self, position: &Vector3<f64>) -> Option<LightSample> {
+        let to_light = self.position - position;
+        let distance_squared = to_light.magnitude_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(-direction.dot(&self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            intensity: self.intensity * falloff / distance_squared,
+        })
+    }
+}
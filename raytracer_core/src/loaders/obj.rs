@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::dielectric::Dielectric;
+use crate::materials::lambertian_diffuse::Lambertian;
+use crate::materials::material::Material;
+use crate::materials::metal::Metal;
+use crate::materials::texture::Texture;
+use crate::materials::texture_source::TextureSource;
+use crate::shapes::shape::Shape;
+use crate::shapes::triangle_mesh::{MeshError, MeshFace, TriangleMesh};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, std::io::Error),
+    Texture(PathBuf, image::ImageError),
+    Parse {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            ObjError::Texture(path, e) => {
+                write!(f, "could not load texture {}: {}", path.display(), e)
+            }
+            ObjError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io(_, e) => Some(e),
+            ObjError::Texture(_, e) => Some(e),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Material as described in a `.mtl` file, converted to one of ours once we know it's used.
+#[derive(Debug, Clone)]
+struct MtlMaterial {
+    diffuse: Vector3<f64>,
+    specular: Vector3<f64>,
+    specular_exponent: Option<f64>,
+    roughness: Option<f64>,
+    metallic: f64,
+    refraction_idx: f64,
+    dissolve: f64,
+    illumination: u32,
+    diffuse_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Vector3::new(0.8, 0.8, 0.8),
+            specular: Vector3::zeros(),
+            specular_exponent: None,
+            roughness: None,
+            metallic: 0.0,
+            refraction_idx: 1.5,
+            dissolve: 1.0,
+            illumination: 2,
+            diffuse_map: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn roughness(&self) -> f64 {
+        match (self.roughness, self.specular_exponent) {
+            (Some(roughness), _) => roughness,
+            // Usual Blinn-Phong exponent to roughness conversion.
+            (None, Some(exponent)) => (2.0 / (exponent + 2.0)).sqrt(),
+            (None, None) => 0.0,
+        }
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.dissolve < 1.0 || matches!(self.illumination, 4 | 6 | 7 | 9)
+    }
+
+    fn is_metallic(&self) -> bool {
+        self.metallic > 0.5 || self.illumination == 3 || self.specular.max() > self.diffuse.max()
+    }
+
+    fn to_material(&self) -> Result<Rc<dyn Material>, ObjError> {
+        let diffuse: Box<dyn TextureSource> = match &self.diffuse_map {
+            Some(path) => match Texture::load_from_file(path, 1.0) {
+                Ok(texture) => Box::new(texture),
+                Err(e) => return Err(ObjError::Texture(path.clone(), e)),
+            },
+            None => Box::new(self.diffuse),
+        };
+        if self.is_transparent() {
+            return Ok(Rc::new(Dielectric::new(diffuse, self.refraction_idx)));
+        }
+        if self.is_metallic() {
+            if self.metallic > 0.5 {
+                return Ok(Rc::new(Metal::new(diffuse, self.roughness())));
+            }
+            return Ok(Rc::new(Metal::new(self.specular, self.roughness())));
+        }
+        Ok(Rc::new(Lambertian::new(diffuse)))
+    }
+}
+
+struct Parser<'a> {
+    file: &'a Path,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn floats(&self, arguments: &[&str], min: usize) -> Result<Vec<f64>, ObjError> {
+        if arguments.len() < min {
+            return Err(self.error(format!(
+                "expected at least {} numbers, got {}",
+                min,
+                arguments.len()
+            )));
+        }
+        arguments
+            .iter()
+            .map(|a| {
+                a.parse::<f64>()
+                    .map_err(|_| self.error(format!("invalid number '{}'", a)))
+            })
+            .collect()
+    }
+
+    fn vector3(&self, arguments: &[&str]) -> Result<Vector3<f64>, ObjError> {
+        let values = self.floats(arguments, 3)?;
+        Ok(Vector3::new(values[0], values[1], values[2]))
+    }
+
+    /// Resolves a 1-based, possibly negative (relative to the end) index.
+    fn index(&self, value: &str, count: usize) -> Result<usize, ObjError> {
+        let index: i64 = value
+            .parse()
+            .map_err(|_| self.error(format!("invalid index '{}'", value)))?;
+        let resolved = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!(
+                "index {} out of bounds, only {} elements defined",
+                index, count
+            )));
+        }
+        Ok(resolved as usize)
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, ObjError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| ObjError::Io(path.to_path_buf(), e))
+}
+
+fn lines(path: &Path) -> Result<Vec<String>, ObjError> {
+    open(path)?
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|e| ObjError::Io(path.to_path_buf(), e))
+}
+
+fn load_mtl(path: &Path, materials: &mut HashMap<String, MtlMaterial>) -> Result<(), ObjError> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut parser = Parser {
+        file: path,
+        line: 0,
+    };
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line_index, line) in lines(path)?.iter().enumerate() {
+        parser.line = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((arguments.join(" "), MtlMaterial::default()));
+            continue;
+        }
+        let material = match &mut current {
+            Some((_, material)) => material,
+            None => return Err(parser.error(format!("'{}' before any newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => material.diffuse = parser.vector3(&arguments)?,
+            "Ks" => material.specular = parser.vector3(&arguments)?,
+            "Ns" => material.specular_exponent = Some(parser.floats(&arguments, 1)?[0]),
+            "Pr" => material.roughness = Some(parser.floats(&arguments, 1)?[0]),
+            "Pm" => material.metallic = parser.floats(&arguments, 1)?[0],
+            "Ni" => material.refraction_idx = parser.floats(&arguments, 1)?[0],
+            "d" => material.dissolve = parser.floats(&arguments, 1)?[0],
+            "Tr" => material.dissolve = 1.0 - parser.floats(&arguments, 1)?[0],
+            "illum" => {
+                material.illumination = arguments
+                    .first()
+                    .and_then(|a| a.parse().ok())
+                    .ok_or_else(|| parser.error("invalid illumination model".to_string()))?
+            }
+            // Options such as `-s 1 1 1` come first, the file name is last.
+            "map_Kd" => match arguments.last() {
+                Some(file) => material.diffuse_map = Some(directory.join(file)),
+                None => return Err(parser.error("map_Kd without file".to_string())),
+            },
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current.take() {
+        materials.insert(name, material);
+    }
+    Ok(())
+}
+
+/// Faces of an `o` object of the file, with the name of their material.
+#[derive(Default)]
+struct ObjObject {
+    faces: Vec<MeshFace>,
+    materials: Vec<String>,
+}
+
+/// Copies the elements of a file buffer used by a mesh in a buffer of its own.
+struct Remap<'a, T> {
+    source: &'a [T],
+    indices: HashMap<usize, usize>,
+    values: Vec<T>,
+}
+
+impl<'a, T: Clone> Remap<'a, T> {
+    fn new(source: &'a [T]) -> Self {
+        Remap {
+            source,
+            indices: HashMap::new(),
+            values: vec![],
+        }
+    }
+
+    fn index(&mut self, source_index: usize) -> usize {
+        let values = &mut self.values;
+        let source = self.source;
+        *self.indices.entry(source_index).or_insert_with(|| {
+            values.push(source[source_index].clone());
+            values.len() - 1
+        })
+    }
+}
+
+/// Loads a Wavefront `.obj` file and the `.mtl` libraries it references.
+///
+/// Each object of the file becomes a `TriangleMesh`. Materials are mapped to ours:
+/// `map_Kd` gives a `Texture`, transparent ones (`d`, `Tr` or `illum` 4, 6, 7, 9) a `Dielectric`,
+/// metallic ones (`Pm`, `illum` 3 or a specular color stronger than the diffuse one) a `Metal`
+/// whose fuzziness comes from `Pr` or `Ns`, and everything else a `Lambertian`.
+pub fn load_obj(path: &Path) -> Result<Vec<Box<dyn Shape>>, ObjError> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut parser = Parser {
+        file: path,
+        line: 0,
+    };
+    let mut positions: Vec<Vector3<f64>> = vec![];
+    // `None` for normals that can't be normalized, faces using them get the geometric normal.
+    let mut normals: Vec<Option<Vector3<f64>>> = vec![];
+    let mut texture_coords: Vec<Vector2<f64>> = vec![];
+    let mut mtl_materials: HashMap<String, MtlMaterial> = HashMap::new();
+    let mut objects: Vec<ObjObject> = vec![ObjObject::default()];
+    let mut current_material = String::new();
+
+    for (line_index, line) in lines(path)?.iter().enumerate() {
+        parser.line = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => positions.push(parser.vector3(&arguments)?),
+            "vn" => normals.push(parser.vector3(&arguments)?.try_normalize(1e-12)),
+            "vt" => {
+                let values = parser.floats(&arguments, 1)?;
+                // Obj texture coordinates start at the bottom of the image, ours at the top.
+                let v = values.get(1).copied().unwrap_or(0.0);
+                texture_coords.push(Vector2::new(values[0], 1.0 - v));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(parser.error("face with less than 3 vertices".to_string()));
+                }
+                let mut vertices = vec![];
+                for vertex in &arguments {
+                    let mut indices = vertex.split('/');
+                    let position = parser.index(indices.next().unwrap_or(""), positions.len())?;
+                    let texture_coord = match indices.next() {
+                        Some(index) if !index.is_empty() => {
+                            Some(parser.index(index, texture_coords.len())?)
+                        }
+                        _ => None,
+                    };
+                    let normal = match indices.next() {
+                        Some(index) if !index.is_empty() => {
+                            let index = parser.index(index, normals.len())?;
+                            normals[index].map(|_| index)
+                        }
+                        _ => None,
+                    };
+                    vertices.push((position, texture_coord, normal));
+                }
+                let object = objects.last_mut().unwrap();
+                let material = match object.materials.iter().position(|m| *m == current_material) {
+                    Some(index) => index,
+                    None => {
+                        object.materials.push(current_material.clone());
+                        object.materials.len() - 1
+                    }
+                };
+                // Polygons are split in a triangle fan.
+                for i in 1..vertices.len() - 1 {
+                    let triangle = [vertices[0], vertices[i], vertices[i + 1]];
+                    object.faces.push(MeshFace {
+                        positions: [triangle[0].0, triangle[1].0, triangle[2].0],
+                        texture_coords: match (triangle[0].1, triangle[1].1, triangle[2].1) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        },
+                        normals: match (triangle[0].2, triangle[1].2, triangle[2].2) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        },
+                        material,
+                    });
+                }
+            }
+            "o" => objects.push(ObjObject::default()),
+            "usemtl" => {
+                let name = arguments.join(" ");
+                if !mtl_materials.contains_key(&name) {
+                    return Err(parser.error(format!("unknown material '{}'", name)));
+                }
+                current_material = name;
+            }
+            "mtllib" => {
+                for library in &arguments {
+                    load_mtl(&directory.join(library), &mut mtl_materials)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut shapes: Vec<Box<dyn Shape>> = vec![];
+    // Each material is built once and shared by the objects using it.
+    let mut materials_by_name: HashMap<String, Rc<dyn Material>> = HashMap::new();
+    for object in objects {
+        let mut materials = vec![];
+        for name in &object.materials {
+            let material = match materials_by_name.get(name) {
+                Some(material) => Rc::clone(material),
+                None => {
+                    let material = mtl_materials
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_default()
+                        .to_material()?;
+                    materials_by_name.insert(name.clone(), Rc::clone(&material));
+                    material
+                }
+            };
+            materials.push(material);
+        }
+        // Objects share the buffers of the file, only keep what each of them uses.
+        let mut mesh_positions = Remap::new(&positions);
+        let mut mesh_normals = Remap::new(&normals);
+        let mut mesh_texture_coords = Remap::new(&texture_coords);
+        let faces = object
+            .faces
+            .iter()
+            .map(|face| MeshFace {
+                positions: face.positions.map(|i| mesh_positions.index(i)),
+                normals: face.normals.map(|n| n.map(|i| mesh_normals.index(i))),
+                texture_coords: face
+                    .texture_coords
+                    .map(|t| t.map(|i| mesh_texture_coords.index(i))),
+                material: face.material,
+            })
+            .collect();
+        match TriangleMesh::new(
+            mesh_positions.values,
+            mesh_normals.values.into_iter().flatten().collect(),
+            mesh_texture_coords.values,
+            faces,
+            materials,
+        ) {
+            Ok(mesh) => shapes.push(Box::new(mesh)),
+            // Like the object before the first `o` when the file starts with one.
+            Err(MeshError::Empty) => {}
+            Err(MeshError::InvalidFace(_)) => unreachable!("indices are checked while parsing"),
+        }
+    }
+    Ok(shapes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::ray::Ray;
+
+    /// Writes `files` in a directory of their own and returns the path of the first one.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("raytracer_obj_{}_{}", test, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (name, content) in files {
+            std::fs::write(directory.join(name), content).unwrap();
+        }
+        directory.join(files[0].0)
+    }
+
+    fn load_error(path: &Path) -> ObjError {
+        match load_obj(path) {
+            Ok(_) => panic!("{} loaded without error", path.display()),
+            Err(error) => error,
+        }
+    }
+
+    fn parse_error(error: ObjError) -> (PathBuf, usize) {
+        match error {
+            ObjError::Parse { file, line, .. } => (file, line),
+            error => panic!("expected a parse error, got {}", error),
+        }
+    }
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+
+    #[test]
+    fn negative_indices_count_from_the_last_element() {
+        let obj = format!("{}vn 0 0 1\nf -3//-1 -2//-1 -1//-1\n", TRIANGLE);
+        let path = write_files("negative", &[("mesh.obj", &obj)]);
+        let shapes = load_obj(&path).unwrap();
+        let ray = Ray::new(Vector3::new(0.2, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let collision = shapes[0].collide(&ray, 0.0, 10.0).unwrap();
+        assert!((collision.dist_from_origin() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn out_of_bounds_index_reports_its_line() {
+        let obj = format!("{}\nf 1 2 -4\n", TRIANGLE);
+        let path = write_files("out_of_bounds", &[("mesh.obj", &obj)]);
+        assert_eq!(parse_error(load_error(&path)), (path, 5));
+    }
+
+    #[test]
+    fn face_with_two_vertices_reports_its_line() {
+        let obj = format!("{}f 1 2\n", TRIANGLE);
+        let path = write_files("arity", &[("mesh.obj", &obj)]);
+        assert_eq!(parse_error(load_error(&path)), (path, 4));
+    }
+
+    #[test]
+    fn missing_mtllib_is_an_io_error() {
+        let path = write_files("mtllib", &[("mesh.obj", "mtllib missing.mtl\n")]);
+        match load_error(&path) {
+            ObjError::Io(file, _) => assert!(file.ends_with("missing.mtl")),
+            error => panic!("expected an io error, got {}", error),
+        }
+    }
+
+    #[test]
+    fn map_kd_without_file_reports_the_mtl_line() {
+        let path = write_files(
+            "map_kd_without_file",
+            &[
+                ("mesh.obj", "mtllib mesh.mtl\n"),
+                ("mesh.mtl", "newmtl red\nKd 1 0 0\nmap_Kd\n"),
+            ],
+        );
+        let (file, line) = parse_error(load_error(&path));
+        assert!(file.ends_with("mesh.mtl"));
+        assert_eq!(line, 3);
+    }
+
+    #[test]
+    fn missing_map_kd_texture_is_a_texture_error() {
+        let obj = format!("mtllib mesh.mtl\nusemtl red\n{}f 1 2 3\n", TRIANGLE);
+        let path = write_files(
+            "map_kd_missing",
+            &[
+                ("mesh.obj", &obj),
+                ("mesh.mtl", "newmtl red\nmap_Kd missing.png\n"),
+            ],
+        );
+        match load_error(&path) {
+            ObjError::Texture(file, _) => assert!(file.ends_with("missing.png")),
+            error => panic!("expected a texture error, got {}", error),
+        }
+    }
+
+    #[test]
+    fn zero_normal_falls_back_to_the_geometric_normal() {
+        let obj = format!("{}vn 0 0 0\nf 1//1 2//1 3//1\n", TRIANGLE);
+        let path = write_files("zero_normal", &[("mesh.obj", &obj)]);
+        let shapes = load_obj(&path).unwrap();
+        let ray = Ray::new(Vector3::new(0.2, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let normal = shapes[0].collide(&ray, 0.0, 10.0).unwrap().normal();
+        assert!((normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+    }
+}
@@ -0,0 +1,232 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use nalgebra::Vector3;
+
+use crate::shapes::aabb::AABB;
+use crate::shapes::voxel_grid::VoxelGrid;
+
+const HEADER_SIZE: usize = 48;
+const ENCODING_FLOAT32: i32 = 1;
+const ENCODING_UINT8: i32 = 3;
+
+#[derive(Debug)]
+pub enum VolError {
+    Io(PathBuf, std::io::Error),
+    Format(PathBuf, String),
+}
+
+impl fmt::Display for VolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VolError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            VolError::Format(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for VolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VolError::Io(_, e) => Some(e),
+            VolError::Format(..) => None,
+        }
+    }
+}
+
+/// Loads a grid from a Mitsuba `.vol` file, the binary format of its `gridvolume`.
+///
+/// Values are 32 bit floats or bytes (read from 0 to 1), only the first channel is kept.
+/// Floats must be finite and non negative, they are densities.
+pub fn load_vol(path: &Path) -> Result<VoxelGrid, VolError> {
+    let bytes = std::fs::read(path).map_err(|e| VolError::Io(path.to_path_buf(), e))?;
+    let format_error = |message: &str| VolError::Format(path.to_path_buf(), message.to_string());
+    if bytes.len() < HEADER_SIZE || &bytes[0..3] != b"VOL" {
+        return Err(format_error("not a .vol file"));
+    }
+    if bytes[3] != 3 {
+        return Err(format_error("only version 3 is supported"));
+    }
+    let int = |offset: usize| i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let float = |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+    let encoding = int(4);
+    let resolution = [int(8), int(12), int(16)];
+    let channels = int(20);
+    if resolution.iter().any(|&r| r <= 0) || channels <= 0 {
+        return Err(format_error("invalid resolution"));
+    }
+    let resolution = resolution.map(|r| r as usize);
+    let channels = channels as usize;
+    let bounds = AABB::new(
+        Vector3::new(float(24) as f64, float(28) as f64, float(32) as f64),
+        Vector3::new(float(36) as f64, float(40) as f64, float(44) as f64),
+    );
+
+    let value_size = match encoding {
+        ENCODING_FLOAT32 => 4,
+        ENCODING_UINT8 => 1,
+        _ => return Err(format_error("unsupported encoding")),
+    };
+    let voxel_count =
+        voxel_count(&resolution).ok_or_else(|| format_error("resolution too large"))?;
+    let data_size = voxel_count
+        .checked_mul(channels)
+        .and_then(|size| size.checked_mul(value_size))
+        .ok_or_else(|| format_error("resolution too large"))?;
+    let data = &bytes[HEADER_SIZE..];
+    if data.len() < data_size {
+        return Err(format_error("truncated data"));
+    }
+    let values: Vec<f64> = data
+        .chunks_exact(value_size * channels)
+        .take(voxel_count)
+        .map(|voxel| match encoding {
+            ENCODING_FLOAT32 => f32::from_le_bytes(voxel[0..4].try_into().unwrap()) as f64,
+            _ => voxel[0] as f64 / 255.0,
+        })
+        .collect();
+    if let Some(index) = values.iter().position(|value| !value.is_finite()) {
+        return Err(format_error(&format!("voxel {} is not finite", index)));
+    }
+    if let Some(index) = values.iter().position(|&value| value < 0.0) {
+        return Err(format_error(&format!("voxel {} is negative", index)));
+    }
+    Ok(VoxelGrid::new(resolution, bounds, values))
+}
+
+/// Loads a grid from a headerless file of one byte per voxel, read from 0 to 1,
+/// x varying first, then y, then z. Scans are often distributed this way.
+pub fn load_raw(path: &Path, resolution: [usize; 3], bounds: AABB) -> Result<VoxelGrid, VolError> {
+    let bytes = std::fs::read(path).map_err(|e| VolError::Io(path.to_path_buf(), e))?;
+    let voxel_count = voxel_count(&resolution)
+        .ok_or_else(|| VolError::Format(path.to_path_buf(), "resolution too large".to_string()))?;
+    if bytes.len() != voxel_count {
+        return Err(VolError::Format(
+            path.to_path_buf(),
+            format!("expected {} voxels, found {}", voxel_count, bytes.len()),
+        ));
+    }
+    let values = bytes.iter().map(|&value| value as f64 / 255.0).collect();
+    Ok(VoxelGrid::new(resolution, bounds, values))
+}
+
+/// Number of voxels of a grid, `None` if it doesn't fit in memory addresses.
+fn voxel_count(resolution: &[usize; 3]) -> Option<usize> {
+    resolution
+        .iter()
+        .try_fold(1_usize, |count, &r| count.checked_mul(r))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(encoding: i32, resolution: [i32; 3], channels: i32) -> Vec<u8> {
+        let mut bytes = b"VOL\x03".to_vec();
+        for value in [
+            encoding,
+            resolution[0],
+            resolution[1],
+            resolution[2],
+            channels,
+        ]
+        .iter()
+        {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0.0_f32, 0.0, 0.0, 1.0, 1.0, 1.0].iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn write_file(test: &str, bytes: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("raytracer_vol_{}_{}.vol", test, std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn format_error(test: &str, bytes: &[u8]) -> String {
+        let path = write_file(test, bytes);
+        let result = load_vol(&path);
+        std::fs::remove_file(&path).unwrap();
+        match result {
+            Err(VolError::Format(_, message)) => message,
+            Err(error) => panic!("expected a format error, got {}", error),
+            Ok(_) => panic!("loaded without error"),
+        }
+    }
+
+    #[test]
+    fn loads_first_channel_of_bytes() {
+        let mut bytes = header(ENCODING_UINT8, [2, 1, 1], 2);
+        bytes.extend_from_slice(&[0, 9, 255, 9]);
+        let path = write_file("bytes", &bytes);
+        let grid = load_vol(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(grid.resolution(), [2, 1, 1]);
+        assert_eq!(grid.max_value(), 1.0);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(format_error("magic", b"not a volume"), "not a .vol file");
+        let mut bytes = header(ENCODING_UINT8, [1, 1, 1], 1);
+        bytes[3] = 2;
+        assert_eq!(
+            format_error("version", &bytes),
+            "only version 3 is supported"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_header_values() {
+        let bytes = header(ENCODING_UINT8, [1, 0, 1], 1);
+        assert_eq!(format_error("resolution", &bytes), "invalid resolution");
+        let bytes = header(ENCODING_UINT8, [1, 1, 1], -1);
+        assert_eq!(format_error("channels", &bytes), "invalid resolution");
+        let bytes = header(2, [1, 1, 1], 1);
+        assert_eq!(format_error("encoding", &bytes), "unsupported encoding");
+    }
+
+    #[test]
+    fn rejects_overflowing_sizes() {
+        let bytes = header(ENCODING_FLOAT32, [i32::MAX, i32::MAX, i32::MAX], 1);
+        assert_eq!(format_error("voxels", &bytes), "resolution too large");
+        let bytes = header(ENCODING_FLOAT32, [i32::MAX, i32::MAX, 1], i32::MAX);
+        assert_eq!(format_error("data", &bytes), "resolution too large");
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let mut bytes = header(ENCODING_FLOAT32, [2, 2, 2], 1);
+        bytes.extend_from_slice(&[0; 7 * 4]);
+        assert_eq!(format_error("truncated", &bytes), "truncated data");
+    }
+
+    #[test]
+    fn rejects_invalid_densities() {
+        let floats = |values: &[f32]| {
+            let mut bytes = header(ENCODING_FLOAT32, [values.len() as i32, 1, 1], 1);
+            for value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes
+        };
+        assert_eq!(
+            format_error("nan", &floats(&[0.5, f32::NAN])),
+            "voxel 1 is not finite"
+        );
+        assert_eq!(
+            format_error("infinite", &floats(&[f32::INFINITY])),
+            "voxel 0 is not finite"
+        );
+        assert_eq!(
+            format_error("negative", &floats(&[0.5, 0.0, -0.25])),
+            "voxel 2 is negative"
+        );
+    }
+}
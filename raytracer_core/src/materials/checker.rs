@@ -0,0 +1,77 @@
+use crate::materials::texture_source::{Mapping, TextureSource};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Color;
+
+/// Shift of the cells, in fraction of their size. Surfaces often lie on the boundaries,
+/// like a ground at y = 0, rounding would pick a cell on either side from one point to the next.
+const CELL_OFFSET: f64 = 1e-4;
+
+/// Alternates between two textures on a grid of cubes, or of squares with `Mapping::TextureCoordinates`.
+pub struct Checker {
+    even: Box<dyn TextureSource>,
+    odd: Box<dyn TextureSource>,
+    size: f64,
+    mapping: Mapping,
+}
+
+impl Checker {
+    /// `size` is the length of the side of a cell.
+    pub fn new(
+        even: impl TextureSource + 'static,
+        odd: impl TextureSource + 'static,
+        size: f64,
+    ) -> Checker {
+        Checker {
+            even: Box::new(even),
+            odd: Box::new(odd),
+            size,
+            mapping: Mapping::Position,
+        }
+    }
+
+    pub fn with_mapping(mut self, mapping: Mapping) -> Checker {
+        self.mapping = mapping;
+        self
+    }
+}
+
+impl TextureSource for Checker {
+    fn value(&self, collision: &Collision) -> Color {
+        let cell =
+            (self.mapping.point(collision) / self.size).map(|x| (x + CELL_OFFSET).floor() as i64);
+        if (cell.x + cell.y + cell.z).rem_euclid(2) == 0 {
+            self.even.value(collision)
+        } else {
+            self.odd.value(collision)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::plane::Plane;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn ground_at_zero_has_a_steady_pattern() {
+        let ground = Plane::new(
+            Vector3::zeros(),
+            Vector3::new(0.0, 1.0, 0.0),
+            Box::new(Lambertian::new(0.5)),
+        );
+        let checker = Checker::new(0.0, 1.0, 1.0);
+        for &y in [0.0, -0.0, 1e-12, -1e-12].iter() {
+            let value = |x, z| {
+                checker
+                    .value(&Collision::new(1.0, Vector3::new(x, y, z), &ground))
+                    .x
+            };
+            assert_eq!(value(0.5, 0.5), 0.0, "{}", y);
+            assert_eq!(value(1.5, 0.5), 1.0, "{}", y);
+            assert_eq!(value(-0.5, 0.5), 1.0, "{}", y);
+            assert_eq!(value(-0.5, -0.5), 0.0, "{}", y);
+        }
+    }
+}
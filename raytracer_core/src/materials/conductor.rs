@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::microfacet::{fresnel_conductor, Ggx, ShadingFrame};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::{Color, Ray};
+
+/// Rough metal, GGX microfacets reflecting light according to the complex index of refraction of the metal.
+pub struct Conductor {
+    // real and imaginary parts of the index of refraction for red, green and blue
+    eta: Color,
+    k: Color,
+    distribution: Ggx,
+    rng: RefCell<SmallRng>,
+}
+
+impl Conductor {
+    /// `roughness` goes from 0 for a mirror to 1.
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            distribution: Ggx::new(roughness),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Conductor {
+        Conductor::new(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    fn fresnel(&self, cos_i: f64) -> Color {
+        Color::new(
+            fresnel_conductor(cos_i, self.eta.x, self.k.x),
+            fresnel_conductor(cos_i, self.eta.y, self.k.y),
+            fresnel_conductor(cos_i, self.eta.z, self.k.z),
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        if self.distribution.is_smooth() {
+            return Color::zeros();
+        }
+        let (frame, wo, _) = ShadingFrame::facing(ray, collision);
+        let wi = frame.to_local(&direction.normalize());
+        match self.distribution.reflection(&wo, &wi) {
+            Some((value, h)) => self.fresnel(wo.dot(&h)) * value,
+            None => Color::zeros(),
+        }
+    }
+
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+        let (frame, wo, _) = ShadingFrame::facing(ray, collision);
+        let wi = frame.to_local(&direction.normalize());
+        self.distribution.reflection_pdf(&wo, &wi)
+    }
+
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        let (frame, wo, _) = ShadingFrame::facing(ray, collision);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        if self.distribution.is_smooth() {
+            return Some(BsdfSample {
+                direction: frame.to_world(&Vector3::new(-wo.x, -wo.y, wo.z)),
+                weight: self.fresnel(wo.z),
+                pdf: 0.0,
+                is_delta: true,
+            });
+        }
+
+        let wi = self
+            .distribution
+            .sample_reflection(&wo, &mut self.rng.borrow_mut())?;
+        let h = (wo + wi).normalize();
+        // D and the cosines cancel out with the pdf of visible normals.
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: self.fresnel(wo.dot(&h)) * self.distribution.g(&wo, &wi)
+                / self.distribution.g1(&wo),
+            pdf: self.distribution.reflection_pdf(&wo, &wi),
+            is_delta: false,
+        })
+    }
+}
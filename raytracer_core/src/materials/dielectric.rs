@@ -1,25 +1,25 @@
 use std::cell::RefCell;
-use std::f64::consts::PI;
 
 use nalgebra::Vector3;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 
-use crate::materials::material::Material;
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::texture_source::TextureSource;
 use crate::shapes::collision::Collision;
 use crate::shapes::ray::{Color, Ray};
 
 pub struct Dielectric {
-    albedo: Vector3<f64>,
+    albedo: Box<dyn TextureSource>,
     // in fact this is not really a Color, more a RGB % of reflection
     refraction_idx: f64,
     rng: RefCell<SmallRng>,
 }
 
 impl Dielectric {
-    pub fn new(albedo: Vector3<f64>, refraction_idx: f64) -> Dielectric {
+    pub fn new(albedo: impl TextureSource + 'static, refraction_idx: f64) -> Dielectric {
         Dielectric {
-            albedo: albedo / PI,
+            albedo: Box::new(albedo),
             rng: RefCell::new(SmallRng::from_entropy()),
             refraction_idx,
         }
@@ -53,31 +53,29 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, _ray: &Ray, collision: &Collision) -> Color {
-        let _light_vector = collision.normal(); // global lightning, could consider normal to be // with light
-        let light_intensity = 3.0; // global lightning, to be changed
-        let light_color = Color::new(1.0, 1.0, 1.0); // global lightning, to be changed
-
-        Vector3::new(
-            self.albedo.x * light_color.x,
-            self.albedo.y * light_color.y,
-            self.albedo.z * light_color.z,
-        ) * light_intensity
+    fn eval(&self, _ray: &Ray, _collision: &Collision, _direction: &Vector3<f64>) -> Color {
+        Color::zeros()
     }
 
-    fn bounce(&self, ray: &Ray, collision: &Collision) -> Option<Ray> {
-        let reflected = Dielectric::reflect(&ray.direction().normalize(), &collision.normal());
+    fn pdf(&self, _ray: &Ray, _collision: &Collision, _direction: &Vector3<f64>) -> f64 {
+        0.0
+    }
+
+    /// Reflects or refracts with the probability given by the Fresnel term, so it cancels out of the weight.
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        let normal = collision.normal();
+        let reflected = Dielectric::reflect(&ray.direction().normalize(), &normal);
 
         let outward_normal;
         let refaction_index;
         let cosine;
-        let dot_product = ray.direction().dot(&collision.normal());
-        if dot_product > 0.0 {
-            outward_normal = -collision.normal();
+        let dot_product = ray.direction().dot(&normal);
+        if ray.direction().dot(&collision.geometric_normal()) > 0.0 {
+            outward_normal = -normal;
             refaction_index = self.refraction_idx;
             cosine = self.refraction_idx * dot_product / ray.direction().len() as f64
         } else {
-            outward_normal = collision.normal();
+            outward_normal = normal;
             refaction_index = 1.0 / self.refraction_idx;
             cosine = -dot_product / ray.direction().len() as f64
         }
@@ -91,11 +89,17 @@ impl Material for Dielectric {
             reflect_prob = 1.0;
         }
 
-        if reflect_prob > self.rng.borrow_mut().gen_range(0.0, 1.0) {
-            Some(Ray::new(*collision.position(), reflected))
+        let direction = if reflect_prob > self.rng.borrow_mut().gen_range(0.0, 1.0) {
+            reflected
         } else {
-            Some(Ray::new(*collision.position(), refracted.unwrap()))
-        }
+            refracted.unwrap()
+        };
+        Some(BsdfSample {
+            direction: direction.normalize(),
+            weight: self.albedo.value(collision),
+            pdf: 0.0,
+            is_delta: true,
+        })
 
         /*let refract_prop = Dielectric::shlick(cosine, self.refraction_idx);
         if refract_prop > self.rng.borrow_mut().gen_range(0.0, 1.0) {
@@ -0,0 +1,53 @@
+use nalgebra::Vector3;
+
+use crate::materials::material::{BsdfSample, Material};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::{Color, Ray};
+
+/// Emits light uniformly from the side of the surface the normal points to, never reflects.
+pub struct DiffuseLight {
+    emission: Color,
+    two_sided: bool,
+}
+
+impl DiffuseLight {
+    pub fn new(color: Vector3<f64>, intensity: f64) -> DiffuseLight {
+        DiffuseLight {
+            emission: color * intensity,
+            two_sided: false,
+        }
+    }
+
+    pub fn new_two_sided(color: Vector3<f64>, intensity: f64) -> DiffuseLight {
+        DiffuseLight {
+            emission: color * intensity,
+            two_sided: true,
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn eval(&self, _ray: &Ray, _collision: &Collision, _direction: &Vector3<f64>) -> Color {
+        Color::zeros()
+    }
+
+    fn pdf(&self, _ray: &Ray, _collision: &Collision, _direction: &Vector3<f64>) -> f64 {
+        0.0
+    }
+
+    fn sample(&self, _ray: &Ray, _collision: &Collision) -> Option<BsdfSample> {
+        None
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+
+    fn emitted(&self, ray: &Ray, collision: &Collision) -> Color {
+        if self.two_sided || ray.direction().dot(&collision.geometric_normal()) < 0.0 {
+            self.emission
+        } else {
+            Color::zeros()
+        }
+    }
+}
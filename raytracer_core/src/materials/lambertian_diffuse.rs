@@ -5,70 +5,103 @@ use nalgebra::Vector3;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 
-use crate::materials::material::Material;
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::texture_source::TextureSource;
 use crate::shapes::collision::Collision;
 use crate::shapes::ray::{Color, Ray};
 
 pub struct Lambertian {
-    albedo: Vector3<f64>,
+    albedo: Box<dyn TextureSource>,
     // in fact this is not really a Color, more a RGB % of reflection
     rng: RefCell<SmallRng>,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Vector3<f64>) -> Lambertian {
+    pub fn new(albedo: impl TextureSource + 'static) -> Lambertian {
         Lambertian {
-            albedo: albedo / PI,
+            albedo: Box::new(albedo),
             rng: RefCell::new(SmallRng::from_entropy()),
         }
     }
 
     pub fn new_from_hex(color: i64) -> Lambertian {
-        Lambertian {
-            albedo: Vector3::new(
-                (((color & 0xFF0000) >> 16) as f64) / 255.0,
-                (((color & 0x00FF00) >> 8) as f64) / 255.0,
-                ((color & 0x0000FF) as f64) / 255.0,
-            ) / PI,
-            rng: RefCell::new(SmallRng::from_entropy()),
-        }
+        Lambertian::new(Vector3::new(
+            (((color & 0xFF0000) >> 16) as f64) / 255.0,
+            (((color & 0x00FF00) >> 8) as f64) / 255.0,
+            ((color & 0x0000FF) as f64) / 255.0,
+        ))
     }
 }
 
-impl Lambertian {
-    fn random_unit_vector(&self) -> Vector3<f64> {
-        let a = self.rand_range_f64(0.0, TAU);
-        let z = self.rand_range_f64(-1.0, 1.0);
-        let r = (1.0 - z * z).sqrt();
+impl Material for Lambertian {
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        diffuse_eval(&self.albedo.value(collision), ray, collision, direction)
+    }
 
-        Vector3::new(r * a.cos(), r * a.sin(), z)
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        diffuse_pdf(ray, collision, direction)
     }
 
-    fn rand_range_f64(&self, start: f64, stop: f64) -> f64 {
-        self.rng.borrow_mut().gen_range(start, stop)
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        diffuse_sample(
+            &self.albedo.value(collision),
+            ray,
+            collision,
+            &mut self.rng.borrow_mut(),
+        )
     }
 }
 
-impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, collision: &Collision) -> Color {
-        // let target = collision.normal() + random_unit_vector();
-        let light_vector = collision.normal(); // global lightning, could consider normal to be // with light
-        let light_intensity = 3.0; // global lightning, to be changed
-        let light_color = Color::new(1.0, 1.0, 1.0); // global lightning, to be changed
-        let dot_product = f64::max(0.0, collision.normal().dot(&light_vector));
+/// Normal on the side of the surface the ray comes from, so both sides reflect.
+fn facing_normal(ray: &Ray, collision: &Collision) -> Vector3<f64> {
+    let normal = collision.normal();
+    if collision.geometric_normal().dot(ray.direction()) > 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
 
-        Vector3::new(
-            self.albedo.x * light_color.x,
-            self.albedo.y * light_color.y,
-            self.albedo.z * light_color.z,
-        ) * light_intensity
-            * dot_product
+fn diffuse_eval(
+    albedo: &Color,
+    ray: &Ray,
+    collision: &Collision,
+    direction: &Vector3<f64>,
+) -> Color {
+    if facing_normal(ray, collision).dot(direction) > 0.0 {
+        albedo / PI
+    } else {
+        Color::zeros()
     }
+}
 
-    fn bounce(&self, _ray: &Ray, collision: &Collision) -> Option<Ray> {
-        Some(Ray::new(
-            *collision.position(),
-            collision.normal() + self.random_unit_vector(),
-        ))
+fn diffuse_pdf(ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+    // Bounces are cosine distributed around the normal.
+    let cosine = facing_normal(ray, collision).dot(&direction.normalize());
+    f64::max(0.0, cosine) / PI
+}
+
+fn diffuse_sample(
+    albedo: &Color,
+    ray: &Ray,
+    collision: &Collision,
+    rng: &mut SmallRng,
+) -> Option<BsdfSample> {
+    // A point on the unit sphere tangent to the surface gives a cosine distributed direction.
+    let a = rng.gen_range(0.0, TAU);
+    let z = rng.gen_range(-1.0, 1.0);
+    let r = f64::sqrt(1.0 - z * z);
+    let normal = facing_normal(ray, collision);
+    let direction = (normal + Vector3::new(r * a.cos(), r * a.sin(), z)).try_normalize(1e-8)?;
+    let pdf = f64::max(0.0, normal.dot(&direction)) / PI;
+    if pdf <= 0.0 {
+        return None;
     }
+    // The cosine and the 1 / PI of the BRDF cancel out with the pdf.
+    Some(BsdfSample {
+        direction,
+        weight: *albedo,
+        pdf,
+        is_delta: false,
+    })
 }
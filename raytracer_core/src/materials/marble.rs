@@ -0,0 +1,57 @@
+use crate::materials::perlin::Perlin;
+use crate::materials::texture_source::{Mapping, TextureSource};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Color;
+
+const OCTAVES: usize = 7;
+
+/// Veins along the x axis, sine stripes distorted by turbulence.
+pub struct Marble {
+    base: Color,
+    veins: Color,
+    frequency: f64,
+    turbulence: f64,
+    perlin: Perlin,
+    mapping: Mapping,
+}
+
+impl Marble {
+    /// `frequency` is the number of veins per unit of length.
+    pub fn new(base: Color, veins: Color, frequency: f64) -> Marble {
+        Marble {
+            base,
+            veins,
+            frequency,
+            turbulence: 2.0,
+            perlin: Perlin::new(0),
+            mapping: Mapping::Position,
+        }
+    }
+
+    /// How much the veins wander, 0 gives straight stripes.
+    pub fn with_turbulence(mut self, turbulence: f64) -> Marble {
+        self.turbulence = turbulence;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Marble {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: Mapping) -> Marble {
+        self.mapping = mapping;
+        self
+    }
+}
+
+impl TextureSource for Marble {
+    fn value(&self, collision: &Collision) -> Color {
+        let point = self.mapping.point(collision) * self.frequency;
+        let phase = std::f64::consts::PI * point.x
+            + self.turbulence * self.perlin.turbulence(&point, OCTAVES);
+        // Thin veins over a large base area.
+        let vein = (0.5 * (1.0 - phase.sin())).powi(3);
+        self.base * (1.0 - vein) + self.veins * vein
+    }
+}
@@ -1,8 +1,51 @@
+use nalgebra::Vector3;
+
 use crate::shapes::collision::Collision;
 use crate::shapes::ray::{Color, Ray};
 
+/// Direction picked by `Material::sample` to continue a path.
+pub struct BsdfSample {
+    /// Unit vector leaving the surface.
+    pub direction: Vector3<f64>,
+    /// f(wi, wo) * |cos| / pdf, what the light coming back along `direction` is multiplied by.
+    pub weight: Color,
+    /// Probability density (over solid angle) of choosing `direction`, meaningless for delta lobes.
+    pub pdf: f64,
+    /// Perfectly specular lobe (mirror, glass...), `eval` and `pdf` can't reach it.
+    pub is_delta: bool,
+}
+
+/// Bidirectional scattering distribution function of a surface.
+///
+/// Directions are unit vectors pointing away from the surface: `direction` toward where the light comes from,
+/// the opposite of the ray direction toward where it goes.
 pub trait Material {
-    fn scatter(&self, ray: &Ray, collision: &Collision) -> Color;
+    /// f(wi, wo) for light coming from `direction` and leaving toward the ray origin, without the cosine.
+    /// Delta lobes are not included.
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color;
+
+    /// Probability density (over solid angle) of `sample` choosing `direction`, 0 for delta lobes.
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64;
+
+    /// Picks a direction to continue the path, `None` when the light is absorbed.
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample>;
+
+    /// Light emitted by the surface toward the ray origin.
+    fn emitted(&self, _ray: &Ray, _collision: &Collision) -> Color {
+        Color::zeros()
+    }
+
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Scatters light inside a volume rather than on a surface, there is no cosine term.
+    fn is_volumetric(&self) -> bool {
+        false
+    }
 
-    fn bounce(&self, ray: &Ray, collision: &Collision) -> Option<Ray>;
+    /// Normal to shade the collision with instead of the one of the shape, `None` to keep it.
+    fn shading_normal(&self, _collision: &Collision) -> Option<Vector3<f64>> {
+        None
+    }
 }
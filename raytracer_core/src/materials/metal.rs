@@ -2,7 +2,8 @@ use std::f64::consts::{PI, TAU};
 
 use nalgebra::Vector3;
 
-use crate::materials::material::Material;
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::texture_source::TextureSource;
 use crate::shapes::collision::Collision;
 use crate::shapes::ray::{Color, Ray};
 use rand::rngs::SmallRng;
@@ -10,16 +11,19 @@ use rand::{Rng, SeedableRng};
 use std::cell::RefCell;
 
 pub struct Metal {
-    albedo: Vector3<f64>, // in fact this is not really a Color, more a RGB % of reflection
-    fuzziness: f64,
+    albedo: Box<dyn TextureSource>, // in fact this is not really a Color, more a RGB % of reflection
+    fuzziness: Box<dyn TextureSource>,
     rng: RefCell<SmallRng>,
 }
 
 impl Metal {
-    pub fn new(albedo: Vector3<f64>, fuzziness: f64) -> Metal {
+    pub fn new(
+        albedo: impl TextureSource + 'static,
+        fuzziness: impl TextureSource + 'static,
+    ) -> Metal {
         Metal {
-            albedo: albedo / PI,
-            fuzziness,
+            albedo: Box::new(albedo),
+            fuzziness: Box::new(fuzziness),
             rng: RefCell::new(SmallRng::from_entropy()),
         }
     }
@@ -41,27 +45,147 @@ impl Metal {
     }
 }
 
+/// Density over solid angle of `reflected + fuzziness * u` pointing along the unit `direction`,
+/// with `u` uniform on the unit sphere and `reflected` a unit vector.
+///
+/// The tip of the vector is uniform on the sphere of radius `fuzziness` around `reflected`,
+/// each crossing of the line of `direction` with it at distance `t` adds `t² / (4π fuzziness √discriminant)`.
+fn fuzzy_pdf(reflected: &Vector3<f64>, fuzziness: f64, direction: &Vector3<f64>) -> f64 {
+    let along = direction.dot(reflected);
+    let discriminant = along * along - 1.0 + fuzziness * fuzziness;
+    if discriminant <= 0.0 {
+        return 0.0;
+    }
+    let root = discriminant.sqrt();
+    let crossings: f64 = [along - root, along + root]
+        .iter()
+        .filter(|&&t| t > 0.0)
+        .map(|t| t * t)
+        .sum();
+    crossings / (4.0 * PI * fuzziness * root)
+}
+
 impl Material for Metal {
-    fn scatter(&self, _ray: &Ray, collision: &Collision) -> Color {
-        // let target = collision.normal() + random_unit_vector();
-        let _light_vector = collision.normal(); // global lightning, could consider normal to be // with light
-        let light_intensity = 3.0; // global lightning, to be changed
-        let light_color = Color::new(1.0, 1.0, 1.0); // global lightning, to be changed
-
-        Vector3::new(
-            self.albedo.x * light_color.x,
-            self.albedo.y * light_color.y,
-            self.albedo.z * light_color.z,
-        ) * light_intensity
+    /// Fuzzy reflections are a lobe around the mirror direction, a perfect mirror has nothing to evaluate.
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        let cosine = collision.normal().dot(direction);
+        if cosine <= 0.0 {
+            return Color::zeros();
+        }
+        // The sample weight is the albedo.
+        self.albedo.value(collision) * self.pdf(ray, collision, direction) / cosine
     }
 
-    fn bounce(&self, ray: &Ray, collision: &Collision) -> Option<Ray> {
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        let fuzziness = self.fuzziness.scalar(collision);
+        if fuzziness <= 0.0 || collision.normal().dot(direction) <= 0.0 {
+            return 0.0;
+        }
         let reflected = self.reflect(&ray.direction().normalize(), &collision.normal());
-        let direction: Vector3<f64> = reflected + self.fuzziness * self.random_unit_vector();
+        fuzzy_pdf(&reflected, fuzziness, &direction.normalize())
+    }
 
-        if direction.dot(&collision.normal()) < 0.0 {
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        let reflected = self.reflect(&ray.direction().normalize(), &collision.normal());
+        let fuzziness = self.fuzziness.scalar(collision);
+        if fuzziness <= 0.0 {
+            return Some(BsdfSample {
+                direction: reflected,
+                weight: self.albedo.value(collision),
+                pdf: 0.0,
+                is_delta: true,
+            });
+        }
+        let direction = (reflected + fuzziness * self.random_unit_vector()).try_normalize(1e-12)?;
+        // Directions going through the surface are absorbed.
+        if direction.dot(&collision.normal()) <= 0.0 {
             return None;
         }
-        Some(Ray::new(*collision.position(), direction))
+        let pdf = fuzzy_pdf(&reflected, fuzziness, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo.value(collision),
+            pdf,
+            is_delta: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::shape::Shape;
+    use crate::shapes::sphere::Sphere;
+
+    /// Metal sphere of radius 1 at the origin hit at (0, 1, 0) by a ray coming down at 45°.
+    fn check(fuzziness: f64, test: impl Fn(&Metal, &Ray, &Collision)) {
+        let sphere = Sphere::new(
+            Vector3::zeros(),
+            1.0,
+            Box::new(Metal::new(Vector3::new(0.9, 0.8, 0.7), fuzziness)),
+        );
+        let metal = Metal::new(Vector3::new(0.9, 0.8, 0.7), fuzziness);
+        let ray = Ray::new(Vector3::new(-2.0, 3.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let collision = sphere.collide(&ray, 0.0, 10.0).unwrap();
+        test(&metal, &ray, &collision);
+    }
+
+    #[test]
+    fn mirror_is_a_delta_lobe() {
+        check(0.0, |metal, ray, collision| {
+            let sample = metal.sample(ray, collision).unwrap();
+            assert!(sample.is_delta);
+            assert!(
+                (sample.direction - Vector3::new(1.0, 1.0, 0.0).normalize()).magnitude() < 1e-9
+            );
+            assert_eq!(metal.pdf(ray, collision, &sample.direction), 0.0);
+        });
+    }
+
+    #[test]
+    fn fuzzy_samples_match_pdf_and_eval() {
+        for &fuzziness in [0.3, 1.5].iter() {
+            check(fuzziness, |metal, ray, collision| {
+                for _ in 0..1000 {
+                    let sample = match metal.sample(ray, collision) {
+                        Some(sample) => sample,
+                        None => continue,
+                    };
+                    assert!(!sample.is_delta);
+                    assert!(sample.direction.dot(&collision.normal()) > 0.0);
+                    let pdf = metal.pdf(ray, collision, &sample.direction);
+                    assert!(
+                        (pdf - sample.pdf).abs() < 1e-6 * pdf,
+                        "{} {}",
+                        pdf,
+                        sample.pdf
+                    );
+                    let cosine = collision.normal().dot(&sample.direction);
+                    let weight = metal.eval(ray, collision, &sample.direction) * cosine / pdf;
+                    assert!((weight - sample.weight).magnitude() < 1e-9);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn fuzzy_pdf_integrates_to_one() {
+        let reflected = Vector3::new(0.0, 0.0, 1.0);
+        for &fuzziness in [0.3, 0.9, 1.5].iter() {
+            // The density only depends on the angle with `reflected`, midpoint rule over its cosine.
+            let steps = 1_000_000;
+            let step = 2.0 / steps as f64;
+            let integral: f64 = (0..steps)
+                .map(|i| {
+                    let z = -1.0 + (i as f64 + 0.5) * step;
+                    let direction = Vector3::new((1.0 - z * z).sqrt(), 0.0, z);
+                    fuzzy_pdf(&reflected, fuzziness, &direction) * TAU * step
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 0.01, "{} {}", fuzziness, integral);
+        }
     }
 }
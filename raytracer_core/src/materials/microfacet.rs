@@ -0,0 +1,301 @@
+use std::f64::consts::{PI, TAU};
+
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::Rng;
+
+use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::Ray;
+
+/// Below this roughness surfaces are handled as perfectly smooth.
+const SMOOTH_ALPHA: f64 = 1e-3;
+
+/// Orthonormal basis around the shading normal, local vectors have the normal as z.
+pub(crate) struct ShadingFrame {
+    tangent: Vector3<f64>,
+    bitangent: Vector3<f64>,
+    normal: Vector3<f64>,
+}
+
+impl ShadingFrame {
+    pub(crate) fn new(normal: &Vector3<f64>) -> ShadingFrame {
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        ShadingFrame {
+            tangent,
+            bitangent,
+            normal: *normal,
+        }
+    }
+
+    /// Frame on the side of the surface the ray comes from, with the direction toward the ray origin in it
+    /// and whether that side is the one the geometric normal points to.
+    pub(crate) fn facing(ray: &Ray, collision: &Collision) -> (ShadingFrame, Vector3<f64>, bool) {
+        let wo = -ray.direction().normalize();
+        let normal = collision.normal();
+        let is_outside = collision.geometric_normal().dot(&wo) >= 0.0;
+        let frame = ShadingFrame::new(&if is_outside { normal } else { -normal });
+        let local_wo = frame.to_local(&wo);
+        (frame, local_wo, is_outside)
+    }
+
+    pub(crate) fn to_local(&self, v: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(
+            v.dot(&self.tangent),
+            v.dot(&self.bitangent),
+            v.dot(&self.normal),
+        )
+    }
+
+    pub(crate) fn to_world(&self, v: &Vector3<f64>) -> Vector3<f64> {
+        self.tangent * v.x + self.bitangent * v.y + self.normal * v.z
+    }
+}
+
+/// Isotropic GGX (Trowbridge-Reitz) distribution of microfacet normals with Smith shadowing,
+/// vectors are in the local shading frame.
+pub(crate) struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    /// `roughness` goes from 0 for a mirror to 1, it is squared to get a perceptually linear scale.
+    pub(crate) fn new(roughness: f64) -> Ggx {
+        Ggx {
+            alpha: roughness.clamp(0.0, 1.0).powi(2),
+        }
+    }
+
+    pub(crate) fn is_smooth(&self) -> bool {
+        self.alpha < SMOOTH_ALPHA
+    }
+
+    /// Density of microfacets oriented along `h`.
+    pub(crate) fn d(&self, h: &Vector3<f64>) -> f64 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let alpha_squared = self.alpha * self.alpha;
+        let denominator = h.z * h.z * (alpha_squared - 1.0) + 1.0;
+        alpha_squared / (PI * denominator * denominator)
+    }
+
+    fn lambda(&self, w: &Vector3<f64>) -> f64 {
+        let cos_squared = w.z * w.z;
+        if cos_squared <= 0.0 {
+            return f64::INFINITY;
+        }
+        let tan_squared = (1.0 - cos_squared).max(0.0) / cos_squared;
+        (-1.0 + (1.0 + self.alpha * self.alpha * tan_squared).sqrt()) / 2.0
+    }
+
+    /// Fraction of the microfacets visible from `w`.
+    pub(crate) fn g1(&self, w: &Vector3<f64>) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of the microfacets visible from both directions, height correlated.
+    pub(crate) fn g(&self, wo: &Vector3<f64>, wi: &Vector3<f64>) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of `sample_visible_normal` returning `h` when looking from `wo`.
+    pub(crate) fn visible_normal_pdf(&self, wo: &Vector3<f64>, h: &Vector3<f64>) -> f64 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(h).max(0.0) * self.d(h) / wo.z
+    }
+
+    /// Microfacet normal seen from `wo` (above the surface), `u1` and `u2` are uniform between 0 and 1.
+    ///
+    /// From Heitz, "Sampling the GGX Distribution of Visible Normals".
+    pub(crate) fn sample_visible_normal(
+        &self,
+        wo: &Vector3<f64>,
+        u1: f64,
+        u2: f64,
+    ) -> Vector3<f64> {
+        // Stretch the view direction so the distribution becomes a hemisphere.
+        let v = Vector3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalize();
+        let length_squared = v.x * v.x + v.y * v.y;
+        let t1 = if length_squared > 0.0 {
+            Vector3::new(-v.y, v.x, 0.0) / length_squared.sqrt()
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = v.cross(&t1);
+
+        let r = u1.sqrt();
+        let phi = TAU * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + v.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let n = t1 * p1 + t2 * p2 + v * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        Vector3::new(self.alpha * n.x, self.alpha * n.y, n.z.max(0.0)).normalize()
+    }
+
+    /// D * G / (4 cos cos) of the reflection from `wo` to `wi` and the microfacet normal between them,
+    /// the Fresnel term is left to the caller. `None` if one of the directions is below the surface.
+    pub(crate) fn reflection(
+        &self,
+        wo: &Vector3<f64>,
+        wi: &Vector3<f64>,
+    ) -> Option<(f64, Vector3<f64>)> {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return None;
+        }
+        let h = (wo + wi).normalize();
+        Some((self.d(&h) * self.g(wo, wi) / (4.0 * wo.z * wi.z), h))
+    }
+
+    /// Density of `sample_reflection` returning `wi`.
+    pub(crate) fn reflection_pdf(&self, wo: &Vector3<f64>, wi: &Vector3<f64>) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        self.visible_normal_pdf(wo, &h) / (4.0 * wo.dot(&h))
+    }
+
+    /// Reflects `wo` on a visible microfacet, `None` if it goes below the surface.
+    pub(crate) fn sample_reflection(
+        &self,
+        wo: &Vector3<f64>,
+        rng: &mut SmallRng,
+    ) -> Option<Vector3<f64>> {
+        let h = self.sample_visible_normal(wo, rng.gen(), rng.gen());
+        let wi = reflect(wo, &h);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(wi)
+    }
+}
+
+pub(crate) fn reflect(w: &Vector3<f64>, normal: &Vector3<f64>) -> Vector3<f64> {
+    2.0 * w.dot(normal) * normal - w
+}
+
+/// Refracts `w` through the surface of normal `normal` (on the side of `w`),
+/// `eta` is the index of the side it goes to over the one it comes from. `None` on total internal reflection.
+pub(crate) fn refract(w: &Vector3<f64>, normal: &Vector3<f64>, eta: f64) -> Option<Vector3<f64>> {
+    let cos_i = w.dot(normal);
+    let sin_squared_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin_squared_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin_squared_t).sqrt();
+    Some(-w / eta + normal * (cos_i / eta - cos_t))
+}
+
+/// Fraction of light reflected by a dielectric interface, `eta` is the index of the inner side over the outer one.
+pub(crate) fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i, eta)
+    };
+    let cos_i = cos_i.min(1.0);
+    let sin_squared_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin_squared_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_squared_t).sqrt();
+    let r_s = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let r_p = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    (r_s * r_s + r_p * r_p) / 2.0
+}
+
+/// Schlick approximation of the Fresnel term for a reflectance `f0` at normal incidence.
+pub(crate) fn fresnel_schlick(f0: &Vector3<f64>, cos_i: f64) -> Vector3<f64> {
+    f0 + (Vector3::repeat(1.0) - f0) * (1.0 - cos_i.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Fraction of light reflected by a conductor of complex index of refraction `eta + i k`.
+pub(crate) fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_squared = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin_squared = 1.0 - cos_squared;
+    let t0 = eta * eta - k * k - sin_squared;
+    let a_squared_plus_b_squared = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a_squared_plus_b_squared + cos_squared;
+    let a = (0.5 * (a_squared_plus_b_squared + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let r_s = (t1 - t2) / (t1 + t2);
+    let t3 = cos_squared * a_squared_plus_b_squared + sin_squared * sin_squared;
+    let t4 = t2 * sin_squared;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+    (r_s + r_p) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn view(cosine: f64) -> Vector3<f64> {
+        Vector3::new((1.0 - cosine * cosine).sqrt(), 0.0, cosine)
+    }
+
+    #[test]
+    fn visible_normal_pdf_integrates_to_one() {
+        for &roughness in [0.3, 0.7, 1.0].iter() {
+            let ggx = Ggx::new(roughness);
+            for &cosine in [1.0, 0.5, 0.1].iter() {
+                let wo = view(cosine);
+                // Midpoint rule over the hemisphere in spherical coordinates.
+                let (steps_theta, steps_phi) = (500, 200);
+                let (step_theta, step_phi) =
+                    (PI / 2.0 / steps_theta as f64, TAU / steps_phi as f64);
+                let mut integral = 0.0;
+                for i in 0..steps_theta {
+                    let theta = (i as f64 + 0.5) * step_theta;
+                    for j in 0..steps_phi {
+                        let phi = (j as f64 + 0.5) * step_phi;
+                        let h = Vector3::new(
+                            theta.sin() * phi.cos(),
+                            theta.sin() * phi.sin(),
+                            theta.cos(),
+                        );
+                        integral +=
+                            ggx.visible_normal_pdf(&wo, &h) * theta.sin() * step_theta * step_phi;
+                    }
+                }
+                assert!(
+                    (integral - 1.0).abs() < 0.01,
+                    "{} {} {}",
+                    roughness,
+                    cosine,
+                    integral
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reflections_are_sampled_with_their_pdf() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for &roughness in [0.5, 1.0].iter() {
+            let ggx = Ggx::new(roughness);
+            for &cosine in [1.0, 0.5, 0.1].iter() {
+                let wo = view(cosine);
+                // Estimates the integral of cos / π over the hemisphere, 1,
+                // only right if the samples are drawn with the density `reflection_pdf` gives.
+                let count = 100_000;
+                let estimate: f64 = (0..count)
+                    .filter_map(|_| ggx.sample_reflection(&wo, &mut rng))
+                    .map(|wi| wi.z / PI / ggx.reflection_pdf(&wo, &wi))
+                    .sum::<f64>()
+                    / count as f64;
+                assert!(
+                    (estimate - 1.0).abs() < 0.02,
+                    "{} {} {}",
+                    roughness,
+                    cosine,
+                    estimate
+                );
+            }
+        }
+    }
+}
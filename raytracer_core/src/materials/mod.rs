@@ -1,5 +1,18 @@
+pub mod checker;
+pub mod conductor;
 pub mod dielectric;
+pub mod diffuse_light;
 pub mod lambertian_diffuse;
+pub mod marble;
 pub mod material;
 pub mod metal;
+pub mod microfacet;
+pub mod noise;
+pub mod normal_mapped;
+pub mod perlin;
+pub mod phase_function;
+pub mod principled;
+pub mod rough_dielectric;
 pub mod texture;
+pub mod texture_source;
+pub mod wood;
@@ -0,0 +1,57 @@
+use crate::materials::perlin::Perlin;
+use crate::materials::texture_source::{Mapping, TextureSource};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Color;
+
+/// Gray levels between 0 and 1 from Perlin noise.
+pub struct Noise {
+    perlin: Perlin,
+    frequency: f64,
+    octaves: usize,
+    turbulent: bool,
+    mapping: Mapping,
+}
+
+impl Noise {
+    /// Smooth clouds, `frequency` is the number of noise cells per unit of length.
+    pub fn fbm(frequency: f64, octaves: usize) -> Noise {
+        Noise {
+            perlin: Perlin::new(0),
+            frequency,
+            octaves,
+            turbulent: false,
+            mapping: Mapping::Position,
+        }
+    }
+
+    /// Billowy pattern with sharp dark creases.
+    pub fn turbulence(frequency: f64, octaves: usize) -> Noise {
+        Noise {
+            turbulent: true,
+            ..Noise::fbm(frequency, octaves)
+        }
+    }
+
+    /// Another pattern of the same kind.
+    pub fn with_seed(mut self, seed: u64) -> Noise {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: Mapping) -> Noise {
+        self.mapping = mapping;
+        self
+    }
+}
+
+impl TextureSource for Noise {
+    fn value(&self, collision: &Collision) -> Color {
+        let point = self.mapping.point(collision) * self.frequency;
+        let level = if self.turbulent {
+            self.perlin.turbulence(&point, self.octaves)
+        } else {
+            0.5 * (1.0 + self.perlin.fbm(&point, self.octaves))
+        };
+        Color::repeat(level.clamp(0.0, 1.0))
+    }
+}
@@ -0,0 +1,111 @@
+use nalgebra::Vector3;
+
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::texture_source::TextureSource;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::{Color, Ray};
+
+/// Distance between the points where a bump map is read to measure its slopes.
+const BUMP_DELTA: f64 = 1e-3;
+
+enum Perturbation {
+    /// Tangent space normal, x along the tangent, y along the bitangent and z along the normal,
+    /// each component stored from 0 to 1.
+    NormalMap(Box<dyn TextureSource>),
+    /// Height of the surface, the normal tilts along its slopes.
+    Bump {
+        height: Box<dyn TextureSource>,
+        strength: f64,
+    },
+}
+
+/// Wraps a material to shade it with a normal read from a normal map or derived from a bump map,
+/// adding details without more geometry.
+pub struct NormalMapped {
+    material: Box<dyn Material>,
+    perturbation: Perturbation,
+}
+
+impl NormalMapped {
+    /// `normal_map` holds tangent space normals, images should be loaded with `Texture::load_data_from_file`.
+    pub fn new(
+        material: impl Material + 'static,
+        normal_map: impl TextureSource + 'static,
+    ) -> NormalMapped {
+        NormalMapped {
+            material: Box::new(material),
+            perturbation: Perturbation::NormalMap(Box::new(normal_map)),
+        }
+    }
+
+    /// `height` is read as a scalar, `strength` is the height in world units of a value of 1.
+    pub fn bump(
+        material: impl Material + 'static,
+        height: impl TextureSource + 'static,
+        strength: f64,
+    ) -> NormalMapped {
+        NormalMapped {
+            material: Box::new(material),
+            perturbation: Perturbation::Bump {
+                height: Box::new(height),
+                strength,
+            },
+        }
+    }
+}
+
+impl Material for NormalMapped {
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        self.material.eval(ray, collision, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        self.material.pdf(ray, collision, direction)
+    }
+
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        self.material.sample(ray, collision)
+    }
+
+    fn emitted(&self, ray: &Ray, collision: &Collision) -> Color {
+        self.material.emitted(ray, collision)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn is_volumetric(&self) -> bool {
+        self.material.is_volumetric()
+    }
+
+    fn shading_normal(&self, collision: &Collision) -> Option<Vector3<f64>> {
+        // Applied on top of the normal of the wrapped material so perturbations can be stacked.
+        let normal = self
+            .material
+            .shading_normal(collision)
+            .unwrap_or_else(|| collision.geometric_normal());
+        let tangent = collision.tangent();
+        let tangent = (tangent - normal * normal.dot(&tangent)).try_normalize(1e-12)?;
+        let bitangent = normal.cross(&tangent);
+
+        let perturbed = match &self.perturbation {
+            Perturbation::NormalMap(normal_map) => {
+                let local = normal_map.value(collision) * 2.0 - Vector3::repeat(1.0);
+                tangent * local.x + bitangent * local.y + normal * local.z
+            }
+            Perturbation::Bump { height, strength } => {
+                let base = height.scalar(collision);
+                let slope = |axis: &Vector3<f64>| {
+                    let neighbour = collision.at_position(collision.position() + axis * BUMP_DELTA);
+                    (height.scalar(&neighbour) - base) / BUMP_DELTA
+                };
+                normal - (tangent * slope(&tangent) + bitangent * slope(&bitangent)) * *strength
+            }
+        };
+        // Texture coordinates can't always be computed around poles and edges.
+        perturbed
+            .try_normalize(1e-12)
+            .filter(|normal| normal.iter().all(|c| c.is_finite()))
+    }
+}
@@ -0,0 +1,98 @@
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise from Perlin, "Improving Noise", with random gradients on an integer lattice.
+///
+/// The lattice is built from a seed so a texture looks the same from one render to the next.
+pub struct Perlin {
+    gradients: Vec<Vector3<f64>>,
+    permutations: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| {
+                Vector3::new(
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                )
+                .try_normalize(1e-6)
+                .unwrap_or_else(Vector3::x)
+            })
+            .collect();
+        let mut permutation = || {
+            let mut permutation: Vec<usize> = (0..POINT_COUNT).collect();
+            permutation.shuffle(&mut rng);
+            permutation
+        };
+        let permutations = [permutation(), permutation(), permutation()];
+        Perlin {
+            gradients,
+            permutations,
+        }
+    }
+
+    /// Smooth noise roughly between -1 and 1, 0 on every lattice point.
+    pub fn noise(&self, point: &Vector3<f64>) -> f64 {
+        let floor = point.map(f64::floor);
+        let offset = point - floor;
+        // Quintic fade so the noise has continuous second derivatives.
+        let fade = offset.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let corner = Vector3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let gradient = self.gradient(&floor, &corner);
+            let corner = corner.map(|c| c as f64);
+            let weight = (0..3)
+                .map(|axis| {
+                    if corner[axis] > 0.0 {
+                        fade[axis]
+                    } else {
+                        1.0 - fade[axis]
+                    }
+                })
+                .product::<f64>();
+            value += weight * gradient.dot(&(offset - corner));
+        }
+        value
+    }
+
+    /// Fractal Brownian motion, `octaves` layers of noise each twice the frequency and half the amplitude of the previous one.
+    pub fn fbm(&self, point: &Vector3<f64>, octaves: usize) -> f64 {
+        self.octaves(point, octaves, |noise| noise)
+    }
+
+    /// Like `fbm` with the absolute value of each layer, giving creases where the noise crosses 0.
+    pub fn turbulence(&self, point: &Vector3<f64>, octaves: usize) -> f64 {
+        self.octaves(point, octaves, f64::abs)
+    }
+
+    fn octaves(&self, point: &Vector3<f64>, octaves: usize, layer: impl Fn(f64) -> f64) -> f64 {
+        let mut value = 0.0;
+        let mut point = *point;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            value += amplitude * layer(self.noise(&point));
+            point *= 2.0;
+            amplitude /= 2.0;
+        }
+        value
+    }
+
+    fn gradient(&self, floor: &Vector3<f64>, corner: &Vector3<usize>) -> &Vector3<f64> {
+        let index = (0..3).fold(0, |hash, axis| {
+            let coordinate =
+                (floor[axis] as i64 + corner[axis] as i64).rem_euclid(POINT_COUNT as i64);
+            hash ^ self.permutations[axis][coordinate as usize]
+        });
+        &self.gradients[index]
+    }
+}
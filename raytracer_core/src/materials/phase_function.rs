@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::f64::consts::{PI, TAU};
+
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::{BsdfSample, Material};
+use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::{Color, Ray};
+
+/// How light is scattered inside a participating medium, the Henyey-Greenstein model.
+pub struct PhaseFunction {
+    albedo: Color,
+    g: f64,
+    rng: RefCell<SmallRng>,
+}
+
+impl PhaseFunction {
+    /// Light scattered equally in every direction.
+    pub fn isotropic(albedo: Color) -> PhaseFunction {
+        PhaseFunction::henyey_greenstein(albedo, 0.0)
+    }
+
+    /// `g` goes from -1 for light sent back where it comes from to 1 for light going on forward,
+    /// smoke and haze are around 0.5 to 0.8.
+    pub fn henyey_greenstein(albedo: Color, g: f64) -> PhaseFunction {
+        PhaseFunction {
+            albedo,
+            g: g.clamp(-0.99, 0.99),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// Density for the path going on with an angle of cosine `cos` with the ray.
+    fn density(&self, cos: f64) -> f64 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+}
+
+impl Material for PhaseFunction {
+    fn eval(&self, ray: &Ray, _collision: &Collision, direction: &Vector3<f64>) -> Color {
+        let cos = ray.direction().normalize().dot(&direction.normalize());
+        self.albedo * self.density(cos)
+    }
+
+    fn pdf(&self, ray: &Ray, _collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        self.density(ray.direction().normalize().dot(&direction.normalize()))
+    }
+
+    fn sample(&self, ray: &Ray, _collision: &Collision) -> Option<BsdfSample> {
+        let mut rng = self.rng.borrow_mut();
+        let g = self.g;
+        let u: f64 = rng.gen();
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let ratio = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            (1.0 + g * g - ratio * ratio) / (2.0 * g)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = TAU * rng.gen::<f64>();
+
+        let forward = ray.direction().normalize();
+        let (tangent, bitangent) = orthonormal_basis(&forward);
+        let direction = forward * cos + (tangent * phi.cos() + bitangent * phi.sin()) * sin;
+        // The phase function is sampled exactly, only the albedo is left.
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo,
+            pdf: self.density(cos),
+            is_delta: false,
+        })
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,289 @@
+use std::cell::RefCell;
+use std::f64::consts::{PI, TAU};
+
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::microfacet::{fresnel_schlick, Ggx, ShadingFrame};
+use crate::materials::rough_dielectric::{dielectric_eval, dielectric_pdf, sample_dielectric};
+use crate::materials::texture_source::luminance;
+use crate::materials::texture_source::TextureSource;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::{Color, Ray};
+
+/// Keeps every lobe rough enough to be evaluated, mirrors are left to `Conductor` and `Dielectric`.
+const MIN_ROUGHNESS: f64 = 0.05;
+const CLEARCOAT_ROUGHNESS: f64 = 0.1;
+/// Reflectance at normal incidence of the clear coat, a varnish of index of refraction 1.5.
+const CLEARCOAT_F0: f64 = 0.04;
+
+/// Artist friendly material loosely following Burley, "Physically Based Shading at Disney".
+///
+/// A diffuse base, a GGX specular layer tinted by the base color as the surface gets metallic,
+/// rough glass for transmission and a clear coat on top. Every parameter is a `TextureSource`,
+/// so it can be a constant or read from an image.
+pub struct Principled {
+    base_color: Box<dyn TextureSource>,
+    metallic: Box<dyn TextureSource>,
+    roughness: Box<dyn TextureSource>,
+    specular: Box<dyn TextureSource>,
+    clearcoat: Box<dyn TextureSource>,
+    transmission: Box<dyn TextureSource>,
+    refraction_idx: f64,
+    rng: RefCell<SmallRng>,
+}
+
+impl Principled {
+    /// Rough dielectric surface of color `base_color`, other parameters are set with the `with_*` methods.
+    pub fn new(base_color: impl TextureSource + 'static) -> Principled {
+        Principled {
+            base_color: Box::new(base_color),
+            metallic: Box::new(0.0),
+            roughness: Box::new(0.5),
+            specular: Box::new(0.5),
+            clearcoat: Box::new(0.0),
+            transmission: Box::new(0.0),
+            refraction_idx: 1.5,
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// From 0 for a dielectric to 1 for a metal reflecting its base color.
+    pub fn with_metallic(mut self, metallic: impl TextureSource + 'static) -> Principled {
+        self.metallic = Box::new(metallic);
+        self
+    }
+
+    /// From 0 for a polished surface to 1.
+    pub fn with_roughness(mut self, roughness: impl TextureSource + 'static) -> Principled {
+        self.roughness = Box::new(roughness);
+        self
+    }
+
+    /// Amount of specular reflection of the dielectric part, 0.5 is a reflectance of 4% like most materials.
+    pub fn with_specular(mut self, specular: impl TextureSource + 'static) -> Principled {
+        self.specular = Box::new(specular);
+        self
+    }
+
+    /// Strength of a polished varnish layer on top of the surface, from 0 to 1.
+    pub fn with_clearcoat(mut self, clearcoat: impl TextureSource + 'static) -> Principled {
+        self.clearcoat = Box::new(clearcoat);
+        self
+    }
+
+    /// From 0 for an opaque surface to 1 for glass tinted by the base color.
+    pub fn with_transmission(mut self, transmission: impl TextureSource + 'static) -> Principled {
+        self.transmission = Box::new(transmission);
+        self
+    }
+
+    pub fn with_refraction_idx(mut self, refraction_idx: f64) -> Principled {
+        self.refraction_idx = refraction_idx;
+        self
+    }
+
+    fn lobes(&self, collision: &Collision, is_outside: bool) -> Lobes {
+        let base_color = self.base_color.value(collision);
+        let metallic = self.metallic.scalar(collision).clamp(0.0, 1.0);
+        let roughness = self.roughness.scalar(collision).max(MIN_ROUGHNESS);
+        let specular = self.specular.scalar(collision).max(0.0);
+        let clearcoat = self.clearcoat.scalar(collision).clamp(0.0, 1.0);
+        let transmission = self.transmission.scalar(collision).clamp(0.0, 1.0);
+
+        let dielectric = 1.0 - metallic;
+        let specular_color = Color::repeat(0.08 * specular) * dielectric + base_color * metallic;
+        let weights = [
+            dielectric * (1.0 - transmission),
+            1.0 - dielectric * transmission,
+            dielectric * transmission,
+            clearcoat,
+        ];
+        // Specular reflections of dielectrics are weak, don't spend too many samples on them.
+        let importances = [
+            weights[0],
+            weights[1] * luminance(&specular_color).max(0.25),
+            weights[2],
+            weights[3] * 0.25,
+        ];
+        let total: f64 = importances.iter().sum();
+        let probabilities = if total > 0.0 {
+            importances.map(|importance| importance / total)
+        } else {
+            [0.0; 4]
+        };
+        Lobes {
+            base_color,
+            specular_color,
+            distribution: Ggx::new(roughness),
+            clearcoat_distribution: Ggx::new(CLEARCOAT_ROUGHNESS),
+            eta: if is_outside {
+                self.refraction_idx
+            } else {
+                1.0 / self.refraction_idx
+            },
+            weights,
+            probabilities,
+        }
+    }
+}
+
+/// Parameters of a `Principled` material evaluated at a collision, in the local shading frame.
+struct Lobes {
+    base_color: Color,
+    specular_color: Color,
+    distribution: Ggx,
+    clearcoat_distribution: Ggx,
+    eta: f64,
+    // diffuse, specular, transmission and clear coat
+    weights: [f64; 4],
+    probabilities: [f64; 4],
+}
+
+impl Lobes {
+    fn eval(&self, wo: &Vector3<f64>, wi: &Vector3<f64>) -> Color {
+        let mut value = Color::zeros();
+        if wo.z > 0.0 && wi.z > 0.0 {
+            // Only the light not reflected by the specular layer reaches the diffuse base.
+            let transmitted = Color::repeat(1.0) - fresnel_schlick(&self.specular_color, wo.z);
+            value += self.base_color.component_mul(&transmitted) * (self.weights[0] / PI);
+        }
+        if let Some((reflection, h)) = self.distribution.reflection(wo, wi) {
+            value +=
+                fresnel_schlick(&self.specular_color, wo.dot(&h)) * reflection * self.weights[1];
+        }
+        if self.weights[2] > 0.0 {
+            value += self.base_color
+                * dielectric_eval(&self.distribution, wo, wi, self.eta)
+                * self.weights[2];
+        }
+        if self.weights[3] > 0.0 {
+            // Same for the layers under the clear coat.
+            let coat_fresnel = fresnel_schlick(&Color::repeat(CLEARCOAT_F0), wo.z.abs()).x;
+            value *= 1.0 - self.weights[3] * coat_fresnel;
+            if let Some((reflection, h)) = self.clearcoat_distribution.reflection(wo, wi) {
+                let fresnel = fresnel_schlick(&Color::repeat(CLEARCOAT_F0), wo.dot(&h)).x;
+                value += Color::repeat(fresnel * reflection * self.weights[3]);
+            }
+        }
+        value
+    }
+
+    fn pdf(&self, wo: &Vector3<f64>, wi: &Vector3<f64>) -> f64 {
+        let [diffuse, specular, transmission, clearcoat] = self.probabilities;
+        let mut pdf = 0.0;
+        if diffuse > 0.0 && wo.z > 0.0 {
+            pdf += diffuse * wi.z.max(0.0) / PI;
+        }
+        if specular > 0.0 {
+            pdf += specular * self.distribution.reflection_pdf(wo, wi);
+        }
+        if transmission > 0.0 {
+            pdf += transmission * dielectric_pdf(&self.distribution, wo, wi, self.eta);
+        }
+        if clearcoat > 0.0 {
+            pdf += clearcoat * self.clearcoat_distribution.reflection_pdf(wo, wi);
+        }
+        pdf
+    }
+
+    /// Picks a lobe according to `probabilities` and a direction from it.
+    fn sample(&self, wo: &Vector3<f64>, rng: &mut SmallRng) -> Option<Vector3<f64>> {
+        let mut u = rng.gen_range(0.0, 1.0);
+        let mut lobe = 0;
+        while lobe < 3 && u >= self.probabilities[lobe] {
+            u -= self.probabilities[lobe];
+            lobe += 1;
+        }
+        match lobe {
+            0 => {
+                // Cosine distributed direction.
+                let u1: f64 = rng.gen();
+                let r = u1.sqrt();
+                let phi = TAU * rng.gen::<f64>();
+                Some(Vector3::new(
+                    r * phi.cos(),
+                    r * phi.sin(),
+                    (1.0 - u1).sqrt(),
+                ))
+            }
+            1 => self.distribution.sample_reflection(wo, rng),
+            2 => sample_dielectric(&self.distribution, wo, self.eta, rng),
+            _ => self.clearcoat_distribution.sample_reflection(wo, rng),
+        }
+    }
+}
+
+impl Material for Principled {
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        let (frame, wo, is_outside) = ShadingFrame::facing(ray, collision);
+        let wi = frame.to_local(&direction.normalize());
+        self.lobes(collision, is_outside).eval(&wo, &wi)
+    }
+
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        let (frame, wo, is_outside) = ShadingFrame::facing(ray, collision);
+        let wi = frame.to_local(&direction.normalize());
+        self.lobes(collision, is_outside).pdf(&wo, &wi)
+    }
+
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        let (frame, wo, is_outside) = ShadingFrame::facing(ray, collision);
+        let lobes = self.lobes(collision, is_outside);
+        let wi = lobes.sample(&wo, &mut self.rng.borrow_mut())?;
+        // Weighted against every lobe that could have chosen the same direction.
+        let pdf = lobes.pdf(&wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: lobes.eval(&wo, &wi) * wi.z.abs() / pdf,
+            pdf,
+            is_delta: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::shape::Shape;
+    use crate::shapes::sphere::Sphere;
+
+    /// Average sample weight for a ray reaching the top of a sphere with `cosine` to the normal,
+    /// the fraction of the light reflected or transmitted.
+    fn albedo(material: Principled, cosine: f64) -> f64 {
+        let sphere = Sphere::new(Vector3::zeros(), 1.0, Box::new(Principled::new(1.0)));
+        let sine = (1.0 - cosine * cosine).sqrt();
+        let direction = Vector3::new(sine, -cosine, 0.0);
+        let ray = Ray::new(Vector3::new(0.0, 1.0, 0.0) - direction, direction);
+        let collision = sphere.collide(&ray, 0.0, 10.0).unwrap();
+        let count = 20_000;
+        let total: f64 = (0..count)
+            .filter_map(|_| material.sample(&ray, &collision))
+            .map(|sample| luminance(&sample.weight))
+            .sum();
+        total / count as f64
+    }
+
+    #[test]
+    fn white_furnace() {
+        for &cosine in [1.0, 0.5, 0.1, 0.02].iter() {
+            for &roughness in [0.05, 0.5].iter() {
+                let plastic = Principled::new(1.0).with_roughness(roughness);
+                let varnished = Principled::new(1.0)
+                    .with_roughness(roughness)
+                    .with_clearcoat(1.0);
+                for &albedo in [albedo(plastic, cosine), albedo(varnished, cosine)].iter() {
+                    assert!(albedo < 1.03, "{} {} {}", cosine, roughness, albedo);
+                    if cosine == 1.0 {
+                        assert!(albedo > 0.95, "{} {} {}", cosine, roughness, albedo);
+                    }
+                }
+            }
+        }
+    }
+}
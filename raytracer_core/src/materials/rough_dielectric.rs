@@ -0,0 +1,176 @@
+use std::cell::RefCell;
+
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::{BsdfSample, Material};
+use crate::materials::microfacet::{fresnel_dielectric, reflect, refract, Ggx, ShadingFrame};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::{Color, Ray};
+
+/// Frosted glass, GGX microfacets reflecting or refracting light, from Walter et al.,
+/// "Microfacet Models for Refraction through Rough Surfaces".
+///
+/// Like `Dielectric`, radiance isn't scaled when it changes medium.
+pub struct RoughDielectric {
+    albedo: Color,
+    refraction_idx: f64,
+    distribution: Ggx,
+    rng: RefCell<SmallRng>,
+}
+
+impl RoughDielectric {
+    /// `roughness` goes from 0 for a clear glass to 1.
+    pub fn new(albedo: Color, refraction_idx: f64, roughness: f64) -> RoughDielectric {
+        RoughDielectric {
+            albedo,
+            refraction_idx,
+            distribution: Ggx::new(roughness),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// Index of refraction of the side opposite to the ray origin over the one of its side.
+    fn relative_eta(&self, is_outside: bool) -> f64 {
+        if is_outside {
+            self.refraction_idx
+        } else {
+            1.0 / self.refraction_idx
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn eval(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        if self.distribution.is_smooth() {
+            return Color::zeros();
+        }
+        let (frame, wo, is_outside) = ShadingFrame::facing(ray, collision);
+        let wi = frame.to_local(&direction.normalize());
+        self.albedo * dielectric_eval(&self.distribution, &wo, &wi, self.relative_eta(is_outside))
+    }
+
+    fn pdf(&self, ray: &Ray, collision: &Collision, direction: &Vector3<f64>) -> f64 {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+        let (frame, wo, is_outside) = ShadingFrame::facing(ray, collision);
+        let wi = frame.to_local(&direction.normalize());
+        dielectric_pdf(&self.distribution, &wo, &wi, self.relative_eta(is_outside))
+    }
+
+    fn sample(&self, ray: &Ray, collision: &Collision) -> Option<BsdfSample> {
+        let (frame, wo, is_outside) = ShadingFrame::facing(ray, collision);
+        let eta = self.relative_eta(is_outside);
+        let wi = sample_dielectric(&self.distribution, &wo, eta, &mut self.rng.borrow_mut())?;
+        if self.distribution.is_smooth() {
+            // The Fresnel term of the choice between reflection and refraction cancels out of the weight.
+            return Some(BsdfSample {
+                direction: frame.to_world(&wi),
+                weight: self.albedo,
+                pdf: 0.0,
+                is_delta: true,
+            });
+        }
+        // D and the cosines cancel out with the pdf of visible normals.
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: self.albedo * self.distribution.g(&wo, &wi) / self.distribution.g1(&wo),
+            pdf: dielectric_pdf(&self.distribution, &wo, &wi, eta),
+            is_delta: false,
+        })
+    }
+}
+
+/// Microfacet normal turning `wo` into `wi` and whether it's a reflection.
+fn half_vector(wo: &Vector3<f64>, wi: &Vector3<f64>, eta: f64) -> Option<(Vector3<f64>, bool)> {
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return None;
+    }
+    let is_reflection = wi.z > 0.0;
+    let h = if is_reflection {
+        wo + wi
+    } else {
+        wo + wi * eta
+    };
+    let h = h.try_normalize(1e-12)?;
+    let h = if h.z < 0.0 { -h } else { h };
+    // Both directions must be on the right side of the microfacet.
+    if wo.dot(&h) <= 0.0 || (wi.dot(&h) > 0.0) != is_reflection {
+        return None;
+    }
+    Some((h, is_reflection))
+}
+
+/// BSDF of a rough dielectric interface in the local shading frame, `wo` is above the surface
+/// and `eta` is the index of refraction below it over the one above.
+pub(crate) fn dielectric_eval(
+    distribution: &Ggx,
+    wo: &Vector3<f64>,
+    wi: &Vector3<f64>,
+    eta: f64,
+) -> f64 {
+    let (h, is_reflection) = match half_vector(wo, wi, eta) {
+        Some(half_vector) => half_vector,
+        None => return 0.0,
+    };
+    let fresnel = fresnel_dielectric(wo.dot(&h), eta);
+    let d_g = distribution.d(&h) * distribution.g(wo, wi);
+    if is_reflection {
+        fresnel * d_g / (4.0 * wo.z * wi.z)
+    } else {
+        let denominator = wo.dot(&h) / eta + wi.dot(&h);
+        (1.0 - fresnel) * d_g * wo.dot(&h) * wi.dot(&h).abs()
+            / (wo.z * wi.z.abs() * denominator * denominator)
+    }
+}
+
+/// Density of `sample_dielectric` returning `wi`.
+pub(crate) fn dielectric_pdf(
+    distribution: &Ggx,
+    wo: &Vector3<f64>,
+    wi: &Vector3<f64>,
+    eta: f64,
+) -> f64 {
+    let (h, is_reflection) = match half_vector(wo, wi, eta) {
+        Some(half_vector) => half_vector,
+        None => return 0.0,
+    };
+    let fresnel = fresnel_dielectric(wo.dot(&h), eta);
+    let normal_pdf = distribution.visible_normal_pdf(wo, &h);
+    if is_reflection {
+        fresnel * normal_pdf / (4.0 * wo.dot(&h))
+    } else {
+        let denominator = wo.dot(&h) / eta + wi.dot(&h);
+        (1.0 - fresnel) * normal_pdf * wi.dot(&h).abs() / (denominator * denominator)
+    }
+}
+
+/// Reflects or refracts `wo` on a visible microfacet with the probability given by the Fresnel term.
+pub(crate) fn sample_dielectric(
+    distribution: &Ggx,
+    wo: &Vector3<f64>,
+    eta: f64,
+    rng: &mut SmallRng,
+) -> Option<Vector3<f64>> {
+    if wo.z <= 0.0 {
+        return None;
+    }
+    let h = if distribution.is_smooth() {
+        Vector3::z()
+    } else {
+        distribution.sample_visible_normal(wo, rng.gen(), rng.gen())
+    };
+    let fresnel = fresnel_dielectric(wo.dot(&h), eta);
+    let wi = if rng.gen_range(0.0, 1.0) < fresnel {
+        reflect(wo, &h)
+    } else {
+        refract(wo, &h, eta)?
+    };
+    // Microfacets can send the direction to the wrong side of the surface.
+    if (wi.z > 0.0) != (wi.dot(&h) > 0.0) {
+        return None;
+    }
+    Some(wi)
+}
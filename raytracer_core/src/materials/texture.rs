@@ -1,62 +1,323 @@
 use std::path::Path;
 
-use image::{DynamicImage, GenericImageView, ImageResult, Pixel};
+use image::ImageResult;
 
-use crate::materials::material::Material;
+use crate::materials::texture_source::TextureSource;
 use crate::shapes::collision::Collision;
-use crate::shapes::ray::{Color, Ray};
+use crate::shapes::ray::Color;
 
-enum Pattern {
-    Simple,
+/// What the image looks like outside of the texture coordinates 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    /// Tiles the image.
+    Repeat,
+    /// Extends the pixels of the edges.
+    Clamp,
+    /// Tiles the image, flipping every other tile.
+    Mirror,
+    /// Stretches the image once over the texture coordinates whatever the scale, edges are extended.
     ExactFit,
 }
 
+impl Pattern {
+    /// Index of the texel read for `index` on an axis of `size` texels.
+    fn wrap(&self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let index = match self {
+            Pattern::Repeat => index.rem_euclid(size),
+            Pattern::Clamp | Pattern::ExactFit => index.clamp(0, size - 1),
+            Pattern::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            }
+        };
+        index as usize
+    }
+}
+
+/// How texels are combined into the value at a collision.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Closest texel, blocky up close.
+    Nearest,
+    /// Blend of the four closest texels.
+    Bilinear,
+    /// Bilinear lookups in the two levels of the mip chain matching the ray footprint, so the texture
+    /// doesn't shimmer at distance.
+    Trilinear,
+}
+
+/// Image read at the texture coordinates of the collision.
+///
+/// Colors are decoded from sRGB to the linear values the renderer works with.
 pub struct Texture {
-    image: DynamicImage,
+    // full resolution image followed by versions of half the size down to a single texel
+    levels: Vec<MipLevel>,
     pattern: Pattern,
+    filter: Filter,
     scale: f64,
 }
 
 impl Texture {
+    /// `scale` is the number of times the image is repeated per unit of texture coordinates.
     pub fn load_from_file(path: &Path, scale: f64) -> ImageResult<Texture> {
-        match image::open(path) {
-            Err(e) => Err(e),
-            Ok(image) => Ok(Texture {
-                image,
-                scale,
-                pattern: Pattern::Simple,
-            }),
+        Texture::load(path, scale, srgb_to_linear)
+    }
+
+    /// Image holding data like roughness rather than colors, values are kept as they are.
+    pub fn load_data_from_file(path: &Path, scale: f64) -> ImageResult<Texture> {
+        Texture::load(path, scale, |value| value)
+    }
+
+    fn load(path: &Path, scale: f64, decode: fn(f64) -> f64) -> ImageResult<Texture> {
+        let image = image::open(path)?.to_rgb8();
+        let texels = image
+            .pixels()
+            .map(|pixel| Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64))
+            .map(|color| color.map(|value| decode(value / 255.0)))
+            .collect();
+        Ok(Texture::from_texels(
+            image.width() as usize,
+            image.height() as usize,
+            texels,
+            scale,
+        ))
+    }
+
+    /// Texture of linear colors stored row by row, with its mip chain.
+    fn from_texels(width: usize, height: usize, texels: Vec<Color>, scale: f64) -> Texture {
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            texels,
+        }];
+        while let Some(level) = levels.last().unwrap().downsample() {
+            levels.push(level);
+        }
+        Texture {
+            levels,
+            pattern: Pattern::Repeat,
+            filter: Filter::Trilinear,
+            scale,
         }
     }
 
-    fn wrap(&self, val: f64, bound: u32) -> u32 {
-        let signed_bound = bound as i32;
-        let float_coord = val * self.scale * bound as f64;
-        let wrapped_coord = (float_coord as i32) % signed_bound;
-        if wrapped_coord < 0 {
-            (wrapped_coord + signed_bound) as u32
-        } else {
-            wrapped_coord as u32
+    pub fn with_pattern(mut self, pattern: Pattern) -> Texture {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Texture {
+        self.filter = filter;
+        self
+    }
+
+    /// Mip level, with its fractional part, whose texels are the size of `footprint` in texture coordinates.
+    fn level_of_detail(&self, footprint: f64, scale: f64) -> f64 {
+        let base = &self.levels[0];
+        let texels = footprint * scale * base.width.max(base.height) as f64;
+        texels.log2().clamp(0.0, (self.levels.len() - 1) as f64)
+    }
+}
+
+impl TextureSource for Texture {
+    fn value(&self, collision: &Collision) -> Color {
+        let scale = match self.pattern {
+            Pattern::ExactFit => 1.0,
+            _ => self.scale,
+        };
+        let coordinates = collision.texture_coordinates() * scale;
+        let (u, v) = (coordinates.x, coordinates.y);
+        match self.filter {
+            Filter::Nearest => self.levels[0].nearest(u, v, self.pattern),
+            Filter::Bilinear => self.levels[0].bilinear(u, v, self.pattern),
+            Filter::Trilinear => {
+                let lod = self.level_of_detail(collision.texture_footprint(), scale);
+                let level = lod.floor() as usize;
+                let fine = self.levels[level].bilinear(u, v, self.pattern);
+                match self.levels.get(level + 1) {
+                    Some(coarse) => {
+                        let t = lod - level as f64;
+                        fine * (1.0 - t) + coarse.bilinear(u, v, self.pattern) * t
+                    }
+                    None => fine,
+                }
+            }
         }
     }
 }
 
-impl Material for Texture {
-    fn scatter(&self, _ray: &Ray, collision: &Collision) -> Color {
-        let text_coord_on_shape = collision.texture_coordinates();
+/// Level of the mip chain, texels are linear colors stored row by row.
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl MipLevel {
+    /// Level of half the size, each texel averaging up to four texels of this one. `None` for a single texel.
+    fn downsample(&self) -> Option<MipLevel> {
+        if self.width == 1 && self.height == 1 {
+            return None;
+        }
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = Color::zeros();
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let source_x = (2 * x + dx).min(self.width - 1);
+                    let source_y = (2 * y + dy).min(self.height - 1);
+                    sum += self.texels[source_y * self.width + source_x];
+                }
+                texels.push(sum / 4.0);
+            }
+        }
+        Some(MipLevel {
+            width,
+            height,
+            texels,
+        })
+    }
+
+    fn texel(&self, x: i64, y: i64, pattern: Pattern) -> Color {
+        let x = pattern.wrap(x, self.width);
+        let y = pattern.wrap(y, self.height);
+        self.texels[y * self.width + x]
+    }
 
-        let tex_x = self.wrap(text_coord_on_shape.x, self.image.width());
-        let tex_y = self.wrap(text_coord_on_shape.y, self.image.height());
-        let rgb = self.image.get_pixel(tex_x, tex_y).to_rgb();
-        // println!("{:?}", rgb);
-        Color::new(
-            rgb[0] as f64 / 255.0,
-            rgb[1] as f64 / 255.0,
-            rgb[2] as f64 / 255.0,
+    fn nearest(&self, u: f64, v: f64, pattern: Pattern) -> Color {
+        self.texel(
+            (u * self.width as f64).floor() as i64,
+            (v * self.height as f64).floor() as i64,
+            pattern,
         )
     }
 
-    fn bounce(&self, _ray: &Ray, _collision: &Collision) -> Option<Ray> {
-        None
+    fn bilinear(&self, u: f64, v: f64, pattern: Pattern) -> Color {
+        // Texel centers are at half coordinates.
+        let x = u * self.width as f64 - 0.5;
+        let y = v * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.texel(x0, y0, pattern) * (1.0 - tx) + self.texel(x0 + 1, y0, pattern) * tx;
+        let bottom =
+            self.texel(x0, y0 + 1, pattern) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1, pattern) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::quad::Quad;
+    use nalgebra::Vector3;
+
+    /// Gray levels of a texture, row by row.
+    fn texture(width: usize, height: usize, grays: &[f64], scale: f64) -> Texture {
+        let texels = grays.iter().map(|&gray| Color::repeat(gray)).collect();
+        Texture::from_texels(width, height, texels, scale)
+    }
+
+    /// Checkerboard of texels of 0 and 1.
+    fn checkerboard(size: usize) -> Texture {
+        let grays: Vec<f64> = (0..size * size)
+            .map(|i| ((i / size + i % size) % 2) as f64)
+            .collect();
+        texture(size, size, &grays, 1.0)
+    }
+
+    /// Value read at (`u`, `v`) by a ray whose footprint is `footprint` wide, in texture coordinates.
+    fn read(texture: &Texture, u: f64, v: f64, footprint: f64) -> f64 {
+        let quad = Quad::new_xy((0.0, 1.0), (0.0, 1.0), 0.0, Box::new(Lambertian::new(0.5)));
+        let collision =
+            Collision::new(1.0, Vector3::new(u, v, 0.0), &quad).with_footprint(footprint);
+        texture.value(&collision).x
+    }
+
+    #[test]
+    fn level_of_detail_follows_the_footprint() {
+        let texture = checkerboard(8);
+        // 8 by 8, 4 by 4, 2 by 2 and 1 texel.
+        assert_eq!(texture.levels.len(), 4);
+        for &(footprint, lod) in [
+            (1e-3, 0.0),
+            (1.0 / 8.0, 0.0),
+            (1.5 / 8.0, 1.5f64.log2()),
+            (1.0 / 4.0, 1.0),
+            (1.0 / 2.0, 2.0),
+            (1.0, 3.0),
+            (10.0, 3.0),
+        ]
+        .iter()
+        {
+            assert!(
+                (texture.level_of_detail(footprint, 1.0) - lod).abs() < 1e-9,
+                "{}",
+                footprint
+            );
+        }
+        // Repeating the image makes its texels smaller.
+        assert!((texture.level_of_detail(1.0 / 8.0, 2.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mip_levels_average_the_texels() {
+        let texture = texture(4, 2, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], 1.0);
+        let sizes: Vec<_> = texture
+            .levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(sizes, vec![(4, 2), (2, 1), (1, 1)]);
+        assert_eq!(
+            texture.levels[1].texels,
+            vec![Color::repeat(2.5), Color::repeat(4.5)]
+        );
+        assert_eq!(texture.levels[2].texels, vec![Color::repeat(3.5)]);
+    }
+
+    #[test]
+    fn filters_read_the_expected_texels() {
+        let texture = |pattern| texture(2, 1, &[0.0, 1.0], 1.0).with_pattern(pattern);
+        let nearest = texture(Pattern::Clamp).with_filter(Filter::Nearest);
+        assert_eq!(read(&nearest, 0.4, 0.5, 0.0), 0.0);
+        assert_eq!(read(&nearest, 0.6, 0.5, 0.0), 1.0);
+        let bilinear = texture(Pattern::Clamp).with_filter(Filter::Bilinear);
+        assert!((read(&bilinear, 0.5, 0.5, 0.0) - 0.5).abs() < 1e-9);
+        // Before the first texel center, clamped to it or blended with the last texel.
+        assert!((read(&bilinear, 0.1, 0.5, 0.0) - 0.0).abs() < 1e-9);
+        let repeated = texture(Pattern::Repeat).with_filter(Filter::Bilinear);
+        assert!((read(&repeated, 0.1, 0.5, 0.0) - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn trilinear_filtering_blurs_with_the_footprint() {
+        let texture = checkerboard(8);
+        // Center of a white texel.
+        let (u, v) = (1.5 / 8.0, 0.5 / 8.0);
+        // Sharp up close, with or without a footprint.
+        assert!((read(&texture, u, v, 0.0) - 1.0).abs() < 1e-9);
+        assert!((read(&texture, u, v, 1.0 / 8.0) - 1.0).abs() < 1e-9);
+        // Halfway to the first level, where the checkerboard is averaged to gray.
+        let halfway = 2f64.sqrt() / 8.0;
+        assert!((read(&texture, u, v, halfway) - 0.75).abs() < 1e-9);
+        // Gray from afar.
+        assert!((read(&texture, u, v, 1.0) - 0.5).abs() < 1e-9);
     }
 }
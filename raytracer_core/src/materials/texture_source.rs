@@ -0,0 +1,70 @@
+use std::rc::Rc;
+
+use nalgebra::Vector3;
+
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Color;
+
+/// Perceived brightness of a linear color (Rec. 709 weights).
+pub(crate) fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Value varying over a surface, like a color read from an image at the texture coordinates of a collision.
+pub trait TextureSource {
+    fn value(&self, collision: &Collision) -> Color;
+
+    /// Single channel value for scalar parameters like roughness, the red one.
+    fn scalar(&self, collision: &Collision) -> f64 {
+        self.value(collision).x
+    }
+}
+
+/// Constant color.
+impl TextureSource for Color {
+    fn value(&self, _collision: &Collision) -> Color {
+        *self
+    }
+}
+
+/// Constant gray level.
+impl TextureSource for f64 {
+    fn value(&self, _collision: &Collision) -> Color {
+        Color::repeat(*self)
+    }
+}
+
+impl<T: TextureSource + ?Sized> TextureSource for Box<T> {
+    fn value(&self, collision: &Collision) -> Color {
+        (**self).value(collision)
+    }
+}
+
+/// Shares a texture between materials, or between parameters of the same material.
+impl<T: TextureSource + ?Sized> TextureSource for Rc<T> {
+    fn value(&self, collision: &Collision) -> Color {
+        (**self).value(collision)
+    }
+}
+
+/// Coordinates procedural textures are evaluated at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mapping {
+    /// Position of the collision in the space of the shape, the texture is carved in its volume
+    /// and follows it when it's moved.
+    Position,
+    /// Texture coordinates of the collision, as (u, v, 0).
+    TextureCoordinates,
+}
+
+impl Mapping {
+    pub(crate) fn point(&self, collision: &Collision) -> Vector3<f64> {
+        match self {
+            Mapping::Position => collision.object_position(),
+            Mapping::TextureCoordinates => {
+                let uv = collision.texture_coordinates();
+                Vector3::new(uv.x, uv.y, 0.0)
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::f64::consts::TAU;
+
+use nalgebra::Vector3;
+
+use crate::materials::perlin::Perlin;
+use crate::materials::texture_source::{Mapping, TextureSource};
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Color;
+
+/// Growth rings around the y axis, wobbling with noise.
+pub struct Wood {
+    light: Color,
+    dark: Color,
+    frequency: f64,
+    turbulence: f64,
+    perlin: Perlin,
+    mapping: Mapping,
+}
+
+impl Wood {
+    /// `frequency` is the number of rings per unit of length.
+    pub fn new(light: Color, dark: Color, frequency: f64) -> Wood {
+        Wood {
+            light,
+            dark,
+            frequency,
+            turbulence: 0.3,
+            perlin: Perlin::new(0),
+            mapping: Mapping::Position,
+        }
+    }
+
+    /// How much the rings are distorted, in ring widths.
+    pub fn with_turbulence(mut self, turbulence: f64) -> Wood {
+        self.turbulence = turbulence;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Wood {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: Mapping) -> Wood {
+        self.mapping = mapping;
+        self
+    }
+}
+
+impl TextureSource for Wood {
+    fn value(&self, collision: &Collision) -> Color {
+        let point = self.mapping.point(collision) * self.frequency;
+        // The grain is stretched along the trunk.
+        let grain = Vector3::new(point.x, point.y * 0.1, point.z);
+        let radius = (point.x * point.x + point.z * point.z).sqrt()
+            + self.turbulence * self.perlin.fbm(&grain, 3);
+        // Late wood is a thin dark band at the end of each ring.
+        let ring = (0.5 * (1.0 - (TAU * radius).cos())).powi(4);
+        self.light * (1.0 - ring) + self.dark * ring
+    }
+}
@@ -0,0 +1,152 @@
+use nalgebra::Vector3;
+
+use crate::shapes::ray::Ray;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AABB {
+    min: Vector3<f64>,
+    max: Vector3<f64>,
+}
+
+impl AABB {
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>) -> AABB {
+        AABB { min, max }
+    }
+
+    /// Box containing nothing, neutral element of `surrounding`.
+    pub fn empty() -> AABB {
+        AABB {
+            min: Vector3::repeat(f64::INFINITY),
+            max: Vector3::repeat(f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn min(&self) -> &Vector3<f64> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Vector3<f64> {
+        &self.max
+    }
+
+    pub fn centroid(&self) -> Vector3<f64> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extent(&self) -> Vector3<f64> {
+        (self.max - self.min).map(|e| e.max(0.0))
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.extent();
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn contains(&self, point: &Vector3<f64>) -> bool {
+        (0..3).all(|axis| point[axis] >= self.min[axis] && point[axis] <= self.max[axis])
+    }
+
+    /// Union of both boxes.
+    pub fn surrounding(&self, other: &AABB) -> AABB {
+        AABB {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn expand(&self, margin: f64) -> AABB {
+        AABB {
+            min: self.min.add_scalar(-margin),
+            max: self.max.add_scalar(margin),
+        }
+    }
+
+    /// Slab test, returns true if the ray crosses the box between `t_min` and `t_max`.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        let inverse_direction = ray.direction().map(|d| 1.0 / d);
+        self.hit_inverse(ray.origin(), &inverse_direction, t_min, t_max)
+    }
+
+    /// Same as `hit` with the inverse of the ray direction precomputed, used when testing many boxes.
+    pub fn hit_inverse(
+        &self,
+        origin: &Vector3<f64>,
+        inverse_direction: &Vector3<f64>,
+        t_min: f64,
+        t_max: f64,
+    ) -> bool {
+        self.clip(origin, inverse_direction, t_min, t_max).is_some()
+    }
+
+    /// Part of the ray between `t_min` and `t_max` inside the box, as an interval of distances along it.
+    pub fn intersection(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let inverse_direction = ray.direction().map(|d| 1.0 / d);
+        self.clip(ray.origin(), &inverse_direction, t_min, t_max)
+    }
+
+    fn clip(
+        &self,
+        origin: &Vector3<f64>,
+        inverse_direction: &Vector3<f64>,
+        mut t_min: f64,
+        mut t_max: f64,
+    ) -> Option<(f64, f64)> {
+        for axis in 0..3 {
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse_direction[axis];
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse_direction[axis];
+            if inverse_direction[axis] < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> AABB {
+        AABB::new(Vector3::zeros(), Vector3::repeat(1.0))
+    }
+
+    fn hit(origin: [f64; 3], direction: [f64; 3]) -> bool {
+        let ray = Ray::new(Vector3::from(origin), Vector3::from(direction));
+        unit_box().hit(&ray, 0.0, 100.0)
+    }
+
+    #[test]
+    fn rays_along_an_axis() {
+        assert!(hit([-5.0, 0.5, 0.5], [1.0, 0.0, 0.0]));
+        assert!(hit([0.5, 0.5, 5.0], [0.0, 0.0, -1.0]));
+        assert!(hit([0.5, 0.5, 5.0], [-0.0, 0.0, -1.0]));
+        assert!(!hit([-5.0, 1.5, 0.5], [1.0, 0.0, 0.0]));
+        assert!(!hit([-5.0, 0.5, -0.5], [1.0, 0.0, 0.0]));
+        assert!(!hit([5.0, 0.5, 0.5], [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn rays_in_the_plane_of_a_face() {
+        // 0 * inf is NaN for these axes, the other axes decide.
+        assert!(hit([-5.0, 1.0, 0.5], [1.0, 0.0, 0.0]));
+        assert!(hit([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]));
+        assert!(!hit([-5.0, 1.0, 2.0], [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn intersection_is_clipped_to_the_interval() {
+        let ray = Ray::new(Vector3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 2.0));
+        assert_eq!(unit_box().intersection(&ray, 0.0, 100.0), Some((0.5, 1.0)));
+        assert_eq!(unit_box().intersection(&ray, 0.75, 0.8), Some((0.75, 0.8)));
+        assert_eq!(unit_box().intersection(&ray, 1.5, 100.0), None);
+    }
+}
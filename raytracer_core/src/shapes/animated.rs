@@ -0,0 +1,290 @@
+use std::rc::Rc;
+
+use nalgebra::{Unit, UnitQuaternion, Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+use crate::shapes::transformed::{transformed_bounds, Transform};
+
+use super::shape::Shape;
+
+// Instants between two keyframes where the bounding box is evaluated
+const BOUNDS_STEPS: usize = 16;
+
+/// Placement of a shape at an instant. The shape is scaled, then rotated, then moved,
+/// whatever the order of the calls.
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    time: f64,
+    translation: Vector3<f64>,
+    rotation: UnitQuaternion<f64>,
+    scale: Vector3<f64>,
+}
+
+impl Keyframe {
+    /// Shape as it is at `time`.
+    pub fn new(time: f64) -> Keyframe {
+        Keyframe {
+            time,
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::repeat(1.0),
+        }
+    }
+
+    pub fn translated(mut self, offset: Vector3<f64>) -> Keyframe {
+        self.translation += offset;
+        self
+    }
+
+    /// Rotation of `angle` degrees around `axis`, which goes through the origin of the shape.
+    pub fn rotated(mut self, axis: Vector3<f64>, angle: f64) -> Keyframe {
+        let rotation =
+            UnitQuaternion::from_axis_angle(&Unit::new_normalize(axis), angle.to_radians());
+        self.rotation = rotation * self.rotation;
+        self
+    }
+
+    pub fn scaled(mut self, factors: Vector3<f64>) -> Keyframe {
+        self.scale = self.scale.component_mul(&factors);
+        self
+    }
+
+    fn transform(&self) -> Transform {
+        Transform::from_parts(&self.translation, &self.rotation, &self.scale)
+    }
+
+    /// Placement at `fraction` of the way to `next`, rotations are blended along the shortest arc.
+    fn interpolate(&self, next: &Keyframe, fraction: f64) -> Keyframe {
+        Keyframe {
+            time: self.time + (next.time - self.time) * fraction,
+            translation: self.translation.lerp(&next.translation, fraction),
+            rotation: self.rotation.slerp(&next.rotation, fraction),
+            scale: self.scale.lerp(&next.scale, fraction),
+        }
+    }
+}
+
+/// Shape moving between keyframes, rays see it where it is at their time.
+/// Before the first keyframe and after the last one, the shape doesn't move.
+pub struct Animated {
+    shape: Rc<dyn Shape>,
+    // sorted by time
+    keyframes: Vec<Keyframe>,
+    bounds: Option<AABB>,
+}
+
+impl Animated {
+    /// Panics without keyframes, if a keyframe time is NaN, if a keyframe scales the shape by 0
+    /// or if a scale factor changes sign between two keyframes, which would flatten the shape in between.
+    pub fn new(shape: Rc<dyn Shape>, mut keyframes: Vec<Keyframe>) -> Animated {
+        assert!(!keyframes.is_empty(), "an animation needs keyframes");
+        assert!(
+            keyframes.iter().all(|keyframe| !keyframe.time.is_nan()),
+            "keyframe time is NaN"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        assert!(
+            keyframes
+                .iter()
+                .all(|keyframe| keyframe.scale.iter().all(|&factor| factor != 0.0)),
+            "keyframe scales the shape by 0"
+        );
+        assert!(
+            keyframes
+                .windows(2)
+                .all(|pair| pair[0].scale.component_mul(&pair[1].scale).min() > 0.0),
+            "scale factor changing sign between keyframes"
+        );
+        let mut animated = Animated {
+            shape,
+            keyframes,
+            bounds: None,
+        };
+        animated.bounds = animated
+            .shape
+            .bounding_box()
+            .map(|bounds| animated.swept_bounds(&bounds));
+        animated
+    }
+
+    /// Box containing `bounds` all along the animation, from the boxes at regular instants.
+    ///
+    /// Between two instants, a point rotating by an angle θ gets away from the segment joining
+    /// where it is at both instants by less than θ times its distance to the origin of the shape,
+    /// the boxes are padded by that much so the bulge of rotations stays inside.
+    fn swept_bounds(&self, bounds: &AABB) -> AABB {
+        let last = transformed_bounds(bounds, &self.transform_at(self.last_time()));
+        self.keyframes.windows(2).fold(last, |all, pair| {
+            let (start, end) = (&pair[0], &pair[1]);
+            let scale = start.scale.abs().sup(&end.scale.abs());
+            let radius = [bounds.min(), bounds.max()]
+                .iter()
+                .map(|corner| corner.abs().component_mul(&scale))
+                .fold(Vector3::zeros(), |radius: Vector3<f64>, c| radius.sup(&c))
+                .magnitude();
+            let step_angle = start.rotation.angle_to(&end.rotation) / BOUNDS_STEPS as f64;
+            let padding = radius * step_angle;
+            (0..BOUNDS_STEPS).fold(all, |all, step| {
+                let fraction = step as f64 / BOUNDS_STEPS as f64;
+                let bounds =
+                    transformed_bounds(bounds, &start.interpolate(end, fraction).transform());
+                all.surrounding(&bounds.expand(padding))
+            })
+        })
+    }
+
+    fn first_time(&self) -> f64 {
+        self.keyframes[0].time
+    }
+
+    fn last_time(&self) -> f64 {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    fn keyframe_at(&self, time: f64) -> Keyframe {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        match next {
+            Some(0) => self.keyframes[0].clone(),
+            Some(next) => {
+                let (before, after) = (&self.keyframes[next - 1], &self.keyframes[next]);
+                before.interpolate(after, (time - before.time) / (after.time - before.time))
+            }
+            None => self.keyframes[self.keyframes.len() - 1].clone(),
+        }
+    }
+
+    pub fn transform_at(&self, time: f64) -> Transform {
+        self.keyframe_at(time).transform()
+    }
+
+    fn ray_to_object(&self, ray: &Ray, transform: &Transform) -> Ray {
+        Ray::new(
+            transform.point_to_object(ray.origin()),
+            transform.vector_to_object(ray.direction()),
+        )
+        .with_spread(ray.spread())
+        .with_time(ray.time())
+    }
+}
+
+impl Shape for Animated {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let transform = self.transform_at(ray.time());
+        let collision = self
+            .shape
+            .collide(&self.ray_to_object(ray, &transform), t_min, t_max)?;
+        Some(collision.transformed(&Rc::new(transform)))
+    }
+
+    fn all_collisions(&self, ray: &Ray) -> Vec<Collision<'_>> {
+        let transform = Rc::new(self.transform_at(ray.time()));
+        self.shape
+            .all_collisions(&self.ray_to_object(ray, &transform))
+            .into_iter()
+            .map(|collision| collision.transformed(&transform))
+            .collect()
+    }
+
+    /// Normal of the shape at the first keyframe.
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let transform = self.transform_at(self.first_time());
+        let normal = self
+            .shape
+            .normal_at_position(&transform.point_to_object(position));
+        transform.normal_to_world(&normal)
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let transform = self.transform_at(self.first_time());
+        self.shape
+            .texture_coords_at_position(&transform.point_to_object(position))
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.shape.material()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.shape.is_emissive()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        self.bounds
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        let transform = self.transform_at(ray.time());
+        self.shape
+            .transmittance(&self.ray_to_object(ray, &transform), t_min, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::cuboid::Cuboid;
+
+    fn cuboid() -> Rc<dyn Shape> {
+        Rc::new(Cuboid::new(
+            Vector3::new(1.0, -0.5, -0.5),
+            Vector3::new(3.0, 0.5, 0.5),
+            Box::new(Lambertian::new(0.5)),
+        ))
+    }
+
+    #[test]
+    fn bounds_contain_the_whole_rotation() {
+        let y = Vector3::new(0.0, 1.0, 0.0);
+        let keyframes = vec![
+            Keyframe::new(0.0),
+            Keyframe::new(1.0)
+                .rotated(y, 170.0)
+                .scaled(Vector3::repeat(2.0)),
+            Keyframe::new(2.0)
+                .rotated(y, -100.0)
+                .translated(Vector3::new(0.0, 3.0, 0.0)),
+        ];
+        let animated = Animated::new(cuboid(), keyframes);
+        let bounds = animated.bounding_box().unwrap().expand(1e-9);
+        let shape_bounds = cuboid().bounding_box().unwrap();
+        for step in 0..=2000 {
+            let transform = animated.transform_at(step as f64 / 1000.0);
+            let exact = transformed_bounds(&shape_bounds, &transform);
+            assert!(bounds.contains(exact.min()) && bounds.contains(exact.max()));
+        }
+    }
+
+    #[test]
+    fn collides_where_the_shape_is_at_the_ray_time() {
+        let keyframes = vec![
+            Keyframe::new(0.0),
+            Keyframe::new(1.0).translated(Vector3::new(0.0, 0.0, -2.0)),
+        ];
+        let animated = Animated::new(cuboid(), keyframes);
+        for &(time, expected) in [(0.0, 4.5), (0.5, 5.5), (1.0, 6.5), (3.0, 6.5)].iter() {
+            let ray =
+                Ray::new(Vector3::new(2.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0)).with_time(time);
+            let collision = animated.collide(&ray, 0.0, 100.0).unwrap();
+            assert!((collision.dist_from_origin() - expected).abs() < 1e-9);
+            let normal = collision.normal();
+            assert!((normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NaN")]
+    fn nan_keyframe_time_panics() {
+        Animated::new(cuboid(), vec![Keyframe::new(0.0), Keyframe::new(f64::NAN)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "changing sign")]
+    fn scale_going_through_zero_panics() {
+        let mirrored = Keyframe::new(1.0).scaled(Vector3::new(-1.0, 1.0, 1.0));
+        Animated::new(cuboid(), vec![Keyframe::new(0.0), mirrored]);
+    }
+}
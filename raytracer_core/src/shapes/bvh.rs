@@ -0,0 +1,375 @@
+use nalgebra::Vector3;
+
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+const MAX_SHAPES_PER_LEAF: usize = 4;
+const MAX_DEPTH: usize = 64;
+const SAH_BUCKETS: usize = 12;
+const TRAVERSAL_COST: f64 = 0.125;
+
+enum BvhNode {
+    Leaf {
+        bounds: AABB,
+        start: usize,
+        count: usize,
+    },
+    Branch {
+        bounds: AABB,
+        left: usize,
+        right: usize,
+        axis: usize,
+    },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &AABB {
+        match self {
+            BvhNode::Leaf { bounds, .. } | BvhNode::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a list of shapes.
+///
+/// The hierarchy only stores indices, so the shapes themselves stay where they are
+/// (a `Scene`, the triangles of a mesh...) and are handed back through a callback at traversal.
+/// Shapes without a bounding box (infinite planes...) are always tested.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounding_boxes: &[Option<AABB>]) -> Bvh {
+        let mut indices = vec![];
+        let mut unbounded = vec![];
+        for (index, bounding_box) in bounding_boxes.iter().enumerate() {
+            match bounding_box {
+                Some(_) => indices.push(index),
+                None => unbounded.push(index),
+            }
+        }
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(indices.len() * 2),
+            indices,
+            unbounded,
+        };
+        if !bvh.indices.is_empty() {
+            let boxes: Vec<AABB> = bounding_boxes
+                .iter()
+                .map(|b| b.unwrap_or_else(AABB::empty))
+                .collect();
+            bvh.build(&boxes, 0, bvh.indices.len(), 0);
+        }
+        bvh
+    }
+
+    fn build(&mut self, boxes: &[AABB], start: usize, end: usize, depth: usize) -> usize {
+        let bounds = self.indices[start..end]
+            .iter()
+            .map(|&index| boxes[index])
+            .fold(AABB::empty(), |acc, b| acc.surrounding(&b));
+        let node_index = self.nodes.len();
+        let count = end - start;
+        let centroids_bounds = self.indices[start..end]
+            .iter()
+            .map(|&index| boxes[index].centroid())
+            .fold(AABB::empty(), |acc, c| acc.surrounding(&AABB::new(c, c)));
+        let axis = centroids_bounds.extent().imax();
+        let centroids_extent = centroids_bounds.extent()[axis];
+
+        let split = if count <= 1 || centroids_extent <= 0.0 {
+            None
+        } else if depth > MAX_DEPTH / 2 {
+            // Degenerate splits could overflow the traversal stack, fall back to balanced ones.
+            Some(start + count / 2)
+        } else {
+            self.find_sah_split(boxes, start, end, &bounds, &centroids_bounds, axis)
+        };
+        let middle = match split {
+            Some(middle) => middle,
+            None if count <= MAX_SHAPES_PER_LEAF || centroids_extent <= 0.0 => {
+                self.nodes.push(BvhNode::Leaf {
+                    bounds,
+                    start,
+                    count,
+                });
+                return node_index;
+            }
+            None => start + count / 2,
+        };
+
+        // Reserve the branch slot before its children so the root stays at index 0.
+        self.nodes.push(BvhNode::Leaf {
+            bounds,
+            start,
+            count: 0,
+        });
+        let left = self.build(boxes, start, middle, depth + 1);
+        let right = self.build(boxes, middle, end, depth + 1);
+        self.nodes[node_index] = BvhNode::Branch {
+            bounds,
+            left,
+            right,
+            axis,
+        };
+        node_index
+    }
+
+    /// Sorts the shapes along `axis` and returns where to split them according to the surface area heuristic,
+    /// `None` if keeping them in a single leaf is cheaper.
+    fn find_sah_split(
+        &mut self,
+        boxes: &[AABB],
+        start: usize,
+        end: usize,
+        bounds: &AABB,
+        centroids_bounds: &AABB,
+        axis: usize,
+    ) -> Option<usize> {
+        let axis_min = centroids_bounds.min()[axis];
+        let axis_extent = centroids_bounds.extent()[axis];
+        let bucket_of = |index: usize| {
+            let offset = (boxes[index].centroid()[axis] - axis_min) / axis_extent;
+            ((offset * SAH_BUCKETS as f64) as usize).min(SAH_BUCKETS - 1)
+        };
+
+        let mut buckets = [(0_usize, AABB::empty()); SAH_BUCKETS];
+        for &index in &self.indices[start..end] {
+            let bucket = &mut buckets[bucket_of(index)];
+            bucket.0 += 1;
+            bucket.1 = bucket.1.surrounding(&boxes[index]);
+        }
+
+        // Cost of splitting after each bucket, relative to the cost of testing one shape.
+        let mut best: Option<(usize, f64)> = None;
+        for split in 0..SAH_BUCKETS - 1 {
+            let (left_count, left_bounds) =
+                buckets[..=split].iter().fold((0, AABB::empty()), |acc, b| {
+                    (acc.0 + b.0, acc.1.surrounding(&b.1))
+                });
+            let (right_count, right_bounds) = buckets[split + 1..]
+                .iter()
+                .fold((0, AABB::empty()), |acc, b| {
+                    (acc.0 + b.0, acc.1.surrounding(&b.1))
+                });
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left_count as f64 * left_bounds.surface_area()
+                    + right_count as f64 * right_bounds.surface_area())
+                    / bounds.surface_area();
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some((split, cost));
+            }
+        }
+
+        let count = end - start;
+        let (split, cost) = best?;
+        if count <= MAX_SHAPES_PER_LEAF && cost >= count as f64 {
+            return None;
+        }
+        self.indices[start..end].sort_by_key(|&index| bucket_of(index));
+        Some(start + buckets[..=split].iter().map(|b| b.0).sum::<usize>())
+    }
+
+    /// Calls `visit` with the index of each shape that may be closer than `margin` to `position`:
+    /// the unbounded ones and the ones in a leaf whose bounding box, grown by `margin`, contains it.
+    pub fn visit_containing<F>(&self, position: &Vector3<f64>, margin: f64, mut visit: F)
+    where
+        F: FnMut(usize),
+    {
+        self.unbounded.iter().for_each(|&index| visit(index));
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node = &self.nodes[stack[stack_size]];
+            if !node.bounds().expand(margin).contains(position) {
+                continue;
+            }
+            match node {
+                BvhNode::Leaf { start, count, .. } => self.indices[*start..*start + *count]
+                    .iter()
+                    .for_each(|&index| visit(index)),
+                BvhNode::Branch { left, right, .. } => {
+                    stack[stack_size] = *left;
+                    stack[stack_size + 1] = *right;
+                    stack_size += 2;
+                }
+            }
+        }
+    }
+
+    /// Returns the nearest collision, `collide` is called with the index of each shape
+    /// whose bounding box is crossed and the current search interval.
+    pub fn find_collision<'a, F>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut collide: F,
+    ) -> Option<(Collision<'a>, usize)>
+    where
+        F: FnMut(usize, f64, f64) -> Option<Collision<'a>>,
+    {
+        let mut maybe_collision: Option<(Collision, usize)> = None;
+        let mut closest = t_max;
+
+        for &index in &self.unbounded {
+            if let Some(collision) = collide(index, t_min, closest) {
+                closest = collision.dist_from_origin();
+                maybe_collision = Some((collision, index));
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return maybe_collision;
+        }
+        let inverse_direction = ray.direction().map(|d| 1.0 / d);
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node = &self.nodes[stack[stack_size]];
+            if !node
+                .bounds()
+                .hit_inverse(ray.origin(), &inverse_direction, t_min, closest)
+            {
+                continue;
+            }
+            match node {
+                BvhNode::Leaf { start, count, .. } => {
+                    for &index in &self.indices[*start..*start + *count] {
+                        if let Some(collision) = collide(index, t_min, closest) {
+                            closest = collision.dist_from_origin();
+                            maybe_collision = Some((collision, index));
+                        }
+                    }
+                }
+                BvhNode::Branch {
+                    left, right, axis, ..
+                } => {
+                    // Visit the nearest child first so the farthest one can be culled.
+                    let (near, far) = if ray.direction()[*axis] < 0.0 {
+                        (*right, *left)
+                    } else {
+                        (*left, *right)
+                    };
+                    stack[stack_size] = far;
+                    stack[stack_size + 1] = near;
+                    stack_size += 2;
+                }
+            }
+        }
+        maybe_collision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::plane::Plane;
+    use crate::shapes::shape::Shape;
+    use crate::shapes::sphere::Sphere;
+    use nalgebra::Vector3;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn sphere(center: Vector3<f64>, radius: f64) -> Box<dyn Shape> {
+        Box::new(Sphere::new(center, radius, Box::new(Lambertian::new(0.5))))
+    }
+
+    /// Compares the nearest hit found through the hierarchy with the one of testing every shape.
+    fn assert_same_as_brute_force(shapes: &[Box<dyn Shape>], rng: &mut SmallRng) {
+        let bounding_boxes: Vec<_> = shapes.iter().map(|shape| shape.bounding_box()).collect();
+        let bvh = Bvh::new(&bounding_boxes);
+        for _ in 0..1000 {
+            let origin = Vector3::new(
+                rng.gen_range(-12.0, 12.0),
+                rng.gen_range(-12.0, 12.0),
+                rng.gen_range(-12.0, 12.0),
+            );
+            let direction = Vector3::new(
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+            );
+            let ray = Ray::new(origin, direction);
+            let expected = shapes
+                .iter()
+                .filter_map(|shape| shape.collide(&ray, 0.001, 1000.0))
+                .map(|collision| collision.dist_from_origin())
+                .fold(f64::INFINITY, f64::min);
+            let found = bvh
+                .find_collision(&ray, 0.001, 1000.0, |index, t_min, t_max| {
+                    shapes[index].collide(&ray, t_min, t_max)
+                })
+                .map_or(f64::INFINITY, |(collision, _)| collision.dist_from_origin());
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn finds_the_nearest_hit_of_scattered_shapes() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut shapes: Vec<Box<dyn Shape>> = (0..300)
+            .map(|_| {
+                let center = Vector3::new(
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                );
+                sphere(center, rng.gen_range(0.1, 1.5))
+            })
+            .collect();
+        shapes.push(Box::new(Plane::new(
+            Vector3::new(0.0, -11.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Box::new(Lambertian::new(0.5)),
+        )));
+        assert_same_as_brute_force(&shapes, &mut rng);
+    }
+
+    #[test]
+    fn keeps_shapes_with_the_same_centroid_in_a_leaf() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        // More shapes than fit in a leaf, they can't be split along any axis.
+        let mut shapes: Vec<Box<dyn Shape>> = (1..=3 * MAX_SHAPES_PER_LEAF)
+            .map(|i| sphere(Vector3::new(1.0, 2.0, 3.0), i as f64 * 0.5))
+            .collect();
+        shapes.push(sphere(Vector3::new(-8.0, 0.0, 0.0), 1.0));
+        assert_same_as_brute_force(&shapes, &mut rng);
+
+        let bounding_boxes: Vec<_> = shapes.iter().map(|shape| shape.bounding_box()).collect();
+        let bvh = Bvh::new(&bounding_boxes);
+        let largest_leaf = bvh
+            .nodes
+            .iter()
+            .map(|node| match node {
+                BvhNode::Leaf { count, .. } => *count,
+                BvhNode::Branch { .. } => 0,
+            })
+            .max();
+        assert_eq!(largest_leaf, Some(3 * MAX_SHAPES_PER_LEAF));
+    }
+
+    #[test]
+    fn empty_hierarchy_finds_nothing() {
+        let bvh = Bvh::new(&[]);
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0));
+        assert!(bvh
+            .find_collision(&ray, 0.0, 1.0, |_, _, _| -> Option<Collision> {
+                unreachable!()
+            })
+            .is_none());
+    }
+}
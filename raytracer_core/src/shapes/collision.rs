@@ -1,12 +1,24 @@
-use nalgebra::{Vector2, Vector3};
+use std::rc::Rc;
 
+use nalgebra::{UnitQuaternion, Vector2, Vector3};
+
+use crate::materials::material::BsdfSample;
+use crate::shapes::plane::orthonormal_basis;
 use crate::shapes::ray::{Color, Ray};
 use crate::shapes::shape::Shape;
+use crate::shapes::transformed::Transform;
 
 pub struct Collision<'a> {
     dist_from_origin: f64,
     position: Vector3<f64>,
     shape: &'a dyn Shape,
+    footprint: f64,
+    // from the space of `shape` to the world when it is placed by `Transformed` shapes
+    transform: Option<Rc<Transform>>,
+    // translation of `shape` applied before `transform`, for shapes only moving like `MovingSphere`
+    offset: Vector3<f64>,
+    // the surface is seen from the other side, for surfaces carved out by `Csg` differences
+    flipped: bool,
 }
 
 impl Collision<'_> {
@@ -15,6 +27,53 @@ impl Collision<'_> {
             position,
             shape,
             dist_from_origin,
+            footprint: 0.0,
+            transform: None,
+            offset: Vector3::zeros(),
+            flipped: false,
+        }
+    }
+
+    /// Same collision with its position and the geometry of the shape moved by `transform`.
+    pub(crate) fn transformed(mut self, transform: &Rc<Transform>) -> Self {
+        self.position = transform.point_to_world(&self.position);
+        self.transform = Some(match &self.transform {
+            Some(inner) => Rc::new(inner.then(transform)),
+            None => Rc::clone(transform),
+        });
+        self
+    }
+
+    /// Same collision with its position and the shape moved by `offset`, cheaper than `transformed`.
+    pub(crate) fn translated(mut self, offset: &Vector3<f64>) -> Self {
+        if self.transform.is_some() {
+            let translation =
+                Transform::from_parts(offset, &UnitQuaternion::identity(), &Vector3::repeat(1.0));
+            return self.transformed(&Rc::new(translation));
+        }
+        self.position += offset;
+        self.offset += offset;
+        self
+    }
+
+    /// Same collision with the normal of the surface pointing the other way.
+    pub(crate) fn flipped(mut self) -> Self {
+        self.flipped = !self.flipped;
+        self
+    }
+
+    /// Width of the area of the surface covered by the ray cone.
+    pub fn with_footprint(mut self, footprint: f64) -> Self {
+        self.footprint = footprint;
+        self
+    }
+
+    /// Collision with the same shape at a nearby `position`, to evaluate textures around the hit.
+    pub fn at_position(&self, position: Vector3<f64>) -> Self {
+        Collision {
+            position,
+            transform: self.transform.clone(),
+            ..*self
         }
     }
 
@@ -26,19 +85,112 @@ impl Collision<'_> {
         self.dist_from_origin
     }
 
+    /// Normal used for shading, perturbed by the material when it has a normal or bump map.
     pub fn normal(&self) -> Vector3<f64> {
-        self.shape.normal_at_position(self.position())
+        self.shape
+            .material()
+            .shading_normal(self)
+            .unwrap_or_else(|| self.geometric_normal())
+    }
+
+    /// Normal of the shape, which side of the surface a direction is on should be decided with it.
+    pub fn geometric_normal(&self) -> Vector3<f64> {
+        let normal = self.shape.normal_at_position(&self.object_position());
+        let normal = match &self.transform {
+            Some(transform) => transform.normal_to_world(&normal),
+            None => normal,
+        };
+        if self.flipped {
+            -normal
+        } else {
+            normal
+        }
+    }
+
+    /// Whether the ray goes inside the shape at this collision, for shapes enclosing a volume.
+    pub fn is_entering(&self, ray: &Ray) -> bool {
+        ray.direction().dot(&self.geometric_normal()) < 0.0
+    }
+
+    /// Unit vector orthogonal to `geometric_normal` along which the first texture coordinate grows.
+    pub fn tangent(&self) -> Vector3<f64> {
+        let normal = self.geometric_normal();
+        let tangent = self.shape.tangent_at_position(&self.object_position());
+        let tangent = match &self.transform {
+            Some(transform) => transform.vector_to_world(&tangent),
+            None => tangent,
+        };
+        // Interpolated normals of triangles aren't orthogonal to the triangle.
+        (tangent - normal * normal.dot(&tangent))
+            .try_normalize(1e-12)
+            .unwrap_or_else(|| orthonormal_basis(&normal).0)
+    }
+
+    pub fn emitted(&self, ray: &Ray) -> Color {
+        self.shape.material().emitted(ray, self)
     }
 
-    pub fn color(&self, ray: &Ray) -> Color {
-        self.shape.material().scatter(ray, self)
+    pub fn is_volumetric(&self) -> bool {
+        self.shape.material().is_volumetric()
     }
 
     pub fn texture_coordinates(&self) -> Vector2<f64> {
-        self.shape.texture_coords_at_position(&self.position)
+        self.texture_coordinates_at(&self.position)
+    }
+
+    fn texture_coordinates_at(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        self.shape
+            .texture_coords_at_position(&self.to_object(position))
+    }
+
+    /// Position of the collision in the space of the shape, before it was moved by `Transformed` shapes.
+    pub fn object_position(&self) -> Vector3<f64> {
+        self.to_object(&self.position)
+    }
+
+    /// The shape answers questions about positions in its own space.
+    fn to_object(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let position = match &self.transform {
+            Some(transform) => transform.point_to_object(position),
+            None => *position,
+        };
+        position - self.offset
+    }
+
+    /// Width of the ray footprint in texture coordinates, 0 when the ray has no spread.
+    ///
+    /// Measured along both tangents of the surface, the smallest one is kept so texture seams aren't blurred.
+    pub fn texture_footprint(&self) -> f64 {
+        if self.footprint <= 0.0 {
+            return 0.0;
+        }
+        let coordinates = self.texture_coordinates();
+        let (tangent, bitangent) = orthonormal_basis(&self.geometric_normal());
+        let footprint = [tangent, bitangent]
+            .iter()
+            .map(|axis| {
+                let neighbour = self.position + axis * self.footprint;
+                (self.texture_coordinates_at(&neighbour) - coordinates).magnitude()
+            })
+            .filter(|footprint| footprint.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        if footprint.is_finite() {
+            footprint
+        } else {
+            0.0
+        }
+    }
+
+    /// BSDF of the surface for light coming from `direction` and leaving toward the ray origin.
+    pub fn eval(&self, ray: &Ray, direction: &Vector3<f64>) -> Color {
+        self.shape.material().eval(ray, self, direction)
+    }
+
+    pub fn pdf(&self, ray: &Ray, direction: &Vector3<f64>) -> f64 {
+        self.shape.material().pdf(ray, self, direction)
     }
 
-    pub fn bounce(&self, ray: &Ray) -> Option<Ray> {
-        self.shape.material().bounce(ray, self)
+    pub fn sample(&self, ray: &Ray) -> Option<BsdfSample> {
+        self.shape.material().sample(ray, self)
     }
 }
@@ -0,0 +1,180 @@
+use std::borrow::Borrow;
+use std::f64::consts::PI;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Cone closed by its base, going up along y from the center of the base `base` to its apex.
+///
+/// On the side, the first texture coordinate goes around the axis like on a `Sphere` and the second one
+/// goes from 0 at the base to 1 at the apex. The base is fitted in the square of texture coordinates 0 to 1.
+pub struct Cone {
+    base: Vector3<f64>,
+    radius: f64,
+    height: f64,
+    // radius / height, how much the radius shrinks per unit of height
+    slope: f64,
+    material: Box<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(base: Vector3<f64>, radius: f64, height: f64, material: Box<dyn Material>) -> Cone {
+        Cone {
+            base,
+            radius,
+            height,
+            slope: radius / height,
+            material,
+        }
+    }
+
+    /// Whether `local`, relative to `base`, is closer to the base than to the side.
+    fn is_on_base(&self, local: &Vector3<f64>) -> bool {
+        let side_distance = (local.xz().magnitude() - self.slope * (self.height - local.y)).abs()
+            / (1.0 + self.slope * self.slope).sqrt();
+        local.y.abs() < side_distance
+    }
+}
+
+impl Shape for Cone {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let origin = ray.origin() - self.base;
+        let direction = ray.direction();
+        let mut nearest = None;
+        let mut keep_nearest = |t: f64| {
+            if t > t_min && t < nearest.unwrap_or(t_max) {
+                nearest = Some(t);
+            }
+        };
+
+        // Side: x² + z² = (slope * (height - y))², kept between the base and the apex.
+        let slope_squared = self.slope * self.slope;
+        let below_apex = self.height - origin.y;
+        let a = direction.xz().magnitude_squared() - slope_squared * direction.y * direction.y;
+        let half_b = origin.xz().dot(&direction.xz()) + slope_squared * below_apex * direction.y;
+        let c = origin.xz().magnitude_squared() - slope_squared * below_apex * below_apex;
+        let mut keep_side = |t: f64| {
+            let y = origin.y + t * direction.y;
+            if y >= 0.0 && y <= self.height {
+                keep_nearest(t);
+            }
+        };
+        if a.abs() > 1e-12 {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant > 0.0 {
+                let root = discriminant.sqrt();
+                keep_side((-half_b - root) / a);
+                keep_side((-half_b + root) / a);
+            }
+        } else if half_b.abs() > 1e-12 {
+            // Ray parallel to the side.
+            keep_side(-c / (2.0 * half_b));
+        }
+        if direction.y.abs() > 1e-12 {
+            let t = -origin.y / direction.y;
+            if (origin + direction * t).xz().magnitude_squared() <= self.radius * self.radius {
+                keep_nearest(t);
+            }
+        }
+
+        let hit_distance_from_ray_origin = nearest?;
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            ray.at(hit_distance_from_ray_origin),
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let local = position - self.base;
+        if self.is_on_base(&local) {
+            return Vector3::new(0.0, -1.0, 0.0);
+        }
+        let distance_to_axis = local.xz().magnitude();
+        Vector3::new(local.x, self.slope * distance_to_axis, local.z)
+            .try_normalize(1e-12)
+            // Apex
+            .unwrap_or_else(|| Vector3::new(0.0, 1.0, 0.0))
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let local = position - self.base;
+        if self.is_on_base(&local) {
+            Vector2::new(
+                (1.0 + local.x / self.radius) * 0.5,
+                (1.0 + local.z / self.radius) * 0.5,
+            )
+        } else {
+            Vector2::new(
+                (1.0 + local.z.atan2(local.x) / PI) * 0.5,
+                local.y / self.height,
+            )
+        }
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let local = position - self.base;
+        if self.is_on_base(&local) {
+            return Vector3::new(1.0, 0.0, 0.0);
+        }
+        Vector3::new(-local.z, 0.0, local.x)
+            .try_normalize(1e-12)
+            .unwrap_or_else(|| Vector3::new(1.0, 0.0, 0.0))
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        let radius = self.radius.abs();
+        Some(AABB::new(
+            self.base + Vector3::new(-radius, 0.0, -radius),
+            self.base + Vector3::new(radius, self.height, radius),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    fn cone() -> Cone {
+        Cone::new(Vector3::zeros(), 1.0, 1.0, Box::new(Lambertian::new(0.5)))
+    }
+
+    fn hit(origin: Vector3<f64>, direction: Vector3<f64>) -> Option<(f64, Vector3<f64>)> {
+        let cone = cone();
+        let collision = cone.collide(&Ray::new(origin, direction), 0.0, 100.0)?;
+        Some((collision.dist_from_origin(), collision.normal()))
+    }
+
+    #[test]
+    fn hits_the_side() {
+        let (t, normal) = hit(Vector3::new(5.0, 0.5, 0.0), Vector3::new(-1.0, 0.0, 0.0)).unwrap();
+        assert!((t - 4.5).abs() < 1e-9);
+        let expected = Vector3::new(1.0, 1.0, 0.0).normalize();
+        assert!((normal - expected).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn hits_the_base() {
+        let (t, normal) = hit(Vector3::new(0.5, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((t - 5.0).abs() < 1e-9);
+        assert!((normal - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn misses_the_other_nappe() {
+        // The infinite double cone continues above the apex, the shape doesn't.
+        assert!(hit(Vector3::new(5.0, 1.5, 0.0), Vector3::new(-1.0, 0.0, 0.0)).is_none());
+        assert!(hit(Vector3::new(5.0, 0.5, 0.6), Vector3::new(-1.0, 0.0, 0.0)).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+
+use nalgebra::{Vector2, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::Material;
+use crate::materials::phase_function::PhaseFunction;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Homogeneous volume like smoke or fog, rays go through it and scatter at random distances.
+pub struct ConstantMedium {
+    // `None` fills the whole space
+    boundary: Option<Box<dyn Shape>>,
+    density: f64,
+    phase_function: Box<dyn Material>,
+    rng: RefCell<SmallRng>,
+}
+
+impl ConstantMedium {
+    /// Fills the inside of `boundary`, which must be a closed shape but can be concave or made of several parts,
+    /// its material is ignored.
+    /// `density` is the probability of scattering per unit of length.
+    pub fn new(
+        boundary: Box<dyn Shape>,
+        density: f64,
+        phase_function: PhaseFunction,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary: Some(boundary),
+            ..ConstantMedium::unbounded(density, phase_function)
+        }
+    }
+
+    /// Medium without boundary, for the fog of a `Raytracer`.
+    pub fn unbounded(density: f64, phase_function: PhaseFunction) -> ConstantMedium {
+        ConstantMedium {
+            boundary: None,
+            density,
+            phase_function: Box::new(phase_function),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// Parts of the ray between `t_min` and `t_max` inside the medium, in order along the ray.
+    fn inside(&self, ray: &Ray, t_min: f64, t_max: f64) -> Vec<(f64, f64)> {
+        let boundary = match &self.boundary {
+            Some(boundary) => boundary,
+            None if t_min < t_max => return vec![(t_min, t_max)],
+            None => return vec![],
+        };
+        let collisions = boundary.all_collisions(ray);
+        // The ray can start inside the volume.
+        let mut enter = match collisions.first() {
+            Some(first) if !first.is_entering(ray) => Some(f64::NEG_INFINITY),
+            _ => None,
+        };
+        let mut spans = vec![];
+        for collision in &collisions {
+            let distance = collision.dist_from_origin();
+            match enter {
+                None if collision.is_entering(ray) => enter = Some(distance),
+                Some(start) if !collision.is_entering(ray) => {
+                    spans.push((start, distance));
+                    enter = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = enter {
+            spans.push((start, f64::INFINITY));
+        }
+        spans
+            .into_iter()
+            .map(|(enter, exit)| (enter.max(t_min), exit.min(t_max)))
+            .filter(|(enter, exit)| enter < exit)
+            .collect()
+    }
+}
+
+impl Shape for ConstantMedium {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let ray_length = ray.direction().magnitude();
+        let u: f64 = self.rng.borrow_mut().gen();
+        // Distance travelled inside the medium before scattering, the gaps between the parts don't count.
+        let mut scatter_distance = -(1.0 - u).ln() / self.density;
+        for (enter, exit) in self.inside(ray, t_min, t_max) {
+            let length = (exit - enter) * ray_length;
+            if scatter_distance < length {
+                let hit_distance_from_ray_origin = enter + scatter_distance / ray_length;
+                return Some(Collision::new(
+                    hit_distance_from_ray_origin,
+                    ray.at(hit_distance_from_ray_origin),
+                    self,
+                ));
+            }
+            scatter_distance -= length;
+        }
+        None
+    }
+
+    /// Volumes have no surface, any unit vector will do.
+    fn normal_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+
+    fn texture_coords_at_position(&self, _position: &Vector3<f64>) -> Vector2<f64> {
+        Vector2::zeros()
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.phase_function.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        self.boundary.as_ref()?.bounding_box()
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        let length: f64 = self
+            .inside(ray, t_min, t_max)
+            .iter()
+            .map(|(enter, exit)| exit - enter)
+            .sum();
+        Some((-self.density * length * ray.direction().magnitude()).exp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::csg::Csg;
+    use crate::shapes::sphere::Sphere;
+
+    /// Medium filling two spheres of radius 1 centered at x = 0 and x = 4.
+    fn two_spheres(density: f64) -> ConstantMedium {
+        let sphere = |x| {
+            Box::new(Sphere::new(
+                Vector3::new(x, 0.0, 0.0),
+                1.0,
+                Box::new(Lambertian::new(0.5)),
+            ))
+        };
+        ConstantMedium::new(
+            Box::new(Csg::union(sphere(0.0), sphere(4.0))),
+            density,
+            PhaseFunction::isotropic(Vector3::repeat(1.0)),
+        )
+    }
+
+    #[test]
+    fn every_part_of_the_boundary_is_filled() {
+        let medium = two_spheres(0.5);
+        let ray = Ray::new(Vector3::new(-2.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            medium.inside(&ray, 0.0, 100.0),
+            vec![(1.0, 3.0), (5.0, 7.0)]
+        );
+        let transmittance = medium.transmittance(&ray, 0.0, 100.0).unwrap();
+        assert!((transmittance - (-0.5 * 4.0_f64).exp()).abs() < 1e-9);
+        // From inside the first sphere, and stopping inside the second one.
+        let transmittance = medium.transmittance(&ray, 2.0, 6.0).unwrap();
+        assert!((transmittance - (-0.5 * 2.0_f64).exp()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn collisions_skip_the_gaps() {
+        let medium = two_spheres(0.5);
+        let ray = Ray::new(Vector3::new(-2.0, 0.0, 0.0), Vector3::new(2.0, 0.0, 0.0));
+        for _ in 0..1000 {
+            if let Some(collision) = medium.collide(&ray, 0.0, 100.0) {
+                let x = collision.position().x;
+                assert!(
+                    (-1.0..=1.0).contains(&x) || (3.0..=5.0).contains(&x),
+                    "{}",
+                    x
+                );
+            }
+        }
+        assert!((0..100).any(|_| medium
+            .collide(&ray, 0.0, 100.0)
+            .is_some_and(|collision| collision.position().x > 3.0)));
+    }
+}
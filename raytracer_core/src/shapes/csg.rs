@@ -0,0 +1,263 @@
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+// Distance from a child surface under which a position is considered on it
+const SURFACE_MARGIN: f64 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// Inside either shape.
+    Union,
+    /// Inside both shapes.
+    Intersection,
+    /// Inside the first shape but not the second one.
+    Difference,
+}
+
+impl Operation {
+    fn is_inside(&self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            Operation::Union => inside_left || inside_right,
+            Operation::Intersection => inside_left && inside_right,
+            Operation::Difference => inside_left && !inside_right,
+        }
+    }
+}
+
+/// Combination of two closed shapes, like a lens made of the intersection of two spheres.
+///
+/// The surface keeps the material of the shape it comes from, the walls carved by a difference have
+/// the material of the second shape.
+pub struct Csg {
+    operation: Operation,
+    left: Box<dyn Shape>,
+    right: Box<dyn Shape>,
+    bounds: Option<AABB>,
+}
+
+impl Csg {
+    pub fn new(operation: Operation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        let bounds = match (operation, left.bounding_box(), right.bounding_box()) {
+            (Operation::Union, Some(left), Some(right)) => Some(left.surrounding(&right)),
+            (Operation::Union, _, _) => None,
+            (Operation::Intersection, Some(left), Some(right)) => Some(AABB::new(
+                left.min().sup(right.min()),
+                left.max().inf(right.max()),
+            )),
+            (Operation::Intersection, left, right) => left.or(right),
+            (Operation::Difference, left, _) => left,
+        };
+        Csg {
+            operation,
+            left,
+            right,
+            bounds,
+        }
+    }
+
+    pub fn union(left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        Csg::new(Operation::Union, left, right)
+    }
+
+    pub fn intersection(left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        Csg::new(Operation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        Csg::new(Operation::Difference, left, right)
+    }
+
+    /// Child whose surface goes through `position`, collisions already reference the touched child
+    /// so this is only needed when the combination itself is asked about a position.
+    fn shape_at(&self, position: &Vector3<f64>) -> &dyn Shape {
+        let is_on = |shape: &dyn Shape| {
+            let normal = shape.normal_at_position(position);
+            let ray = Ray::new(position + normal * SURFACE_MARGIN, -normal);
+            shape.collide(&ray, 0.0, 2.0 * SURFACE_MARGIN).is_some()
+        };
+        if !is_on(self.left.as_ref()) && is_on(self.right.as_ref()) {
+            self.right.as_ref()
+        } else {
+            self.left.as_ref()
+        }
+    }
+}
+
+impl Shape for Csg {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        if let Some(bounds) = &self.bounds {
+            if !bounds.hit(ray, t_min, t_max) {
+                return None;
+            }
+        }
+        self.all_collisions(ray).into_iter().find(|collision| {
+            collision.dist_from_origin() > t_min && collision.dist_from_origin() < t_max
+        })
+    }
+
+    /// Walks the collisions of both shapes along the ray, keeping those where the ray goes
+    /// in or out of the combination.
+    fn all_collisions(&self, ray: &Ray) -> Vec<Collision<'_>> {
+        let left = self.left.all_collisions(ray);
+        let right = self.right.all_collisions(ray);
+        // Before the first collision, the ray is inside a shape if it leaves it there.
+        let mut inside_left = left.first().is_some_and(|c| !c.is_entering(ray));
+        let mut inside_right = right.first().is_some_and(|c| !c.is_entering(ray));
+
+        let mut crossings: Vec<_> = left
+            .into_iter()
+            .map(|collision| (collision, true))
+            .chain(right.into_iter().map(|collision| (collision, false)))
+            .collect();
+        crossings.sort_by(|(a, _), (b, _)| a.dist_from_origin().total_cmp(&b.dist_from_origin()));
+
+        let mut collisions = Vec::new();
+        let mut inside = self.operation.is_inside(inside_left, inside_right);
+        for (collision, is_left) in crossings {
+            let entering = collision.is_entering(ray);
+            if is_left {
+                inside_left = entering;
+            } else {
+                inside_right = entering;
+            }
+            let now_inside = self.operation.is_inside(inside_left, inside_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+            // The surface faces outside of the combination.
+            if now_inside == entering {
+                collisions.push(collision);
+            } else {
+                collisions.push(collision.flipped());
+            }
+        }
+        collisions
+    }
+
+    /// Collisions are on the surface of one of the shapes, which answers for them.
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        self.shape_at(position).normal_at_position(position)
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        self.shape_at(position).texture_coords_at_position(position)
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        self.shape_at(position).tangent_at_position(position)
+    }
+
+    /// Collisions reference the touched child, which answers with its own material,
+    /// this is only the one of the first shape.
+    fn material(&self) -> &dyn Material {
+        self.left.material()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.left.is_emissive() || self.right.is_emissive()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::sphere::Sphere;
+
+    /// Spheres of radius 1 centered at x = -0.5 and x = 0.5.
+    fn csg(operation: Operation) -> Csg {
+        let sphere = |x| {
+            Box::new(Sphere::new(
+                Vector3::new(x, 0.0, 0.0),
+                1.0,
+                Box::new(Lambertian::new(0.5)),
+            ))
+        };
+        Csg::new(operation, sphere(-0.5), sphere(0.5))
+    }
+
+    /// Distance and x component of the normal of every collision of a ray going along x from x = -5.
+    fn crossings(csg: &Csg) -> Vec<(f64, f64)> {
+        let ray = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        csg.all_collisions(&ray)
+            .iter()
+            .map(|c| (c.dist_from_origin(), c.normal().x))
+            .collect()
+    }
+
+    fn assert_crossings(actual: Vec<(f64, f64)>, expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual.0 - expected.0).abs() < 1e-9, "{:?}", actual);
+            assert!((actual.1 - expected.1).abs() < 1e-9, "{:?}", actual);
+        }
+    }
+
+    #[test]
+    fn union_keeps_the_outer_surfaces() {
+        assert_crossings(
+            crossings(&csg(Operation::Union)),
+            &[(3.5, -1.0), (6.5, 1.0)],
+        );
+    }
+
+    #[test]
+    fn intersection_keeps_the_inner_surfaces() {
+        assert_crossings(
+            crossings(&csg(Operation::Intersection)),
+            &[(4.5, -1.0), (5.5, 1.0)],
+        );
+    }
+
+    #[test]
+    fn difference_flips_the_carved_surface() {
+        assert_crossings(
+            crossings(&csg(Operation::Difference)),
+            &[(3.5, -1.0), (4.5, 1.0)],
+        );
+    }
+
+    #[test]
+    fn collide_from_inside_the_combination() {
+        let csg = csg(Operation::Intersection);
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0));
+        let collision = csg.collide(&ray, 0.0, 10.0).unwrap();
+        assert!((collision.dist_from_origin() - 0.5).abs() < 1e-9);
+        assert!(csg.collide(&ray, 0.6, 10.0).is_none());
+    }
+
+    #[test]
+    fn disjoint_intersection_is_empty() {
+        let sphere = |x| {
+            Box::new(Sphere::new(
+                Vector3::new(x, 0.0, 0.0),
+                1.0,
+                Box::new(Lambertian::new(0.5)),
+            ))
+        };
+        let csg = Csg::intersection(sphere(-2.0), sphere(2.0));
+        assert!(crossings(&csg).is_empty());
+    }
+
+    #[test]
+    fn positions_are_answered_by_the_shape_they_are_on() {
+        let csg = csg(Operation::Difference);
+        let normal = csg.normal_at_position(&Vector3::new(-0.5, 0.0, 0.0));
+        assert!((normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        let normal = csg.normal_at_position(&Vector3::new(-1.5, 0.0, 0.0));
+        assert!((normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        let normal = csg.normal_at_position(&Vector3::new(-0.5, 1.0, 0.0));
+        assert!((normal - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+    }
+}
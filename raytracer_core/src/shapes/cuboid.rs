@@ -0,0 +1,138 @@
+use std::borrow::Borrow;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Box with faces aligned on the axes, between the corners `min` and `max`.
+/// Each face is mapped to texture coordinates 0 to 1, use `Transformed` to turn it.
+pub struct Cuboid {
+    bounds: AABB,
+    material: Box<dyn Material>,
+}
+
+impl Cuboid {
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>, material: Box<dyn Material>) -> Cuboid {
+        Cuboid {
+            bounds: AABB::new(min.inf(&max), min.sup(&max)),
+            material,
+        }
+    }
+
+    /// Axis of the face closest to `position`, and whether it's the face on the `max` side.
+    fn face(&self, position: &Vector3<f64>) -> (usize, bool) {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        let mut face = (0, false);
+        let mut closest = f64::INFINITY;
+        for axis in 0..3 {
+            for &(is_max, side) in &[(false, min[axis]), (true, max[axis])] {
+                let distance = (position[axis] - side).abs();
+                if distance < closest {
+                    closest = distance;
+                    face = (axis, is_max);
+                }
+            }
+        }
+        face
+    }
+}
+
+impl Shape for Cuboid {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let (entry, exit) = self
+            .bounds
+            .intersection(ray, f64::NEG_INFINITY, f64::INFINITY)?;
+        let hit_distance_from_ray_origin = [entry, exit]
+            .iter()
+            .copied()
+            .find(|&t| t > t_min && t < t_max)?;
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            ray.at(hit_distance_from_ray_origin),
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let (axis, is_max) = self.face(position);
+        let mut normal = Vector3::zeros();
+        normal[axis] = if is_max { 1.0 } else { -1.0 };
+        normal
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let (axis, _) = self.face(position);
+        // Flat boxes have no extent along some axis.
+        let relative =
+            (position - self.bounds.min()).zip_map(&self.bounds.extent(), |offset, extent| {
+                if extent > 0.0 {
+                    offset / extent
+                } else {
+                    0.0
+                }
+            });
+        Vector2::new(relative[(axis + 1) % 3], relative[(axis + 2) % 3])
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let (axis, _) = self.face(position);
+        let mut tangent = Vector3::zeros();
+        tangent[(axis + 1) % 3] = 1.0;
+        tangent
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    fn cuboid(max: Vector3<f64>) -> Cuboid {
+        Cuboid::new(Vector3::zeros(), max, Box::new(Lambertian::new(0.5)))
+    }
+
+    #[test]
+    fn hits_entry_then_exit() {
+        let cuboid = cuboid(Vector3::new(1.0, 2.0, 4.0));
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 1.0), Vector3::new(1.0, 0.0, 0.0));
+        let collisions = cuboid.all_collisions(&ray);
+        assert_eq!(collisions.len(), 2);
+        assert!((collisions[0].dist_from_origin() - 1.0).abs() < 1e-9);
+        assert_eq!(collisions[0].normal(), Vector3::new(-1.0, 0.0, 0.0));
+        assert!((collisions[1].dist_from_origin() - 2.0).abs() < 1e-9);
+        assert_eq!(collisions[1].normal(), Vector3::new(1.0, 0.0, 0.0));
+        // Faces are mapped to the texture coordinates from `min` to `max`.
+        let coordinates = collisions[0].texture_coordinates();
+        assert!((coordinates - Vector2::new(0.5, 0.25)).magnitude() < 1e-9);
+        assert!(cuboid.collide(&ray, 2.5, 10.0).is_none());
+    }
+
+    #[test]
+    fn flat_boxes_have_texture_coordinates() {
+        let cuboid = cuboid(Vector3::new(1.0, 0.0, 1.0));
+        for &(x, z) in [(0.5, 0.5), (0.0, 0.5), (1.0, 1.0)].iter() {
+            let coordinates = cuboid.texture_coords_at_position(&Vector3::new(x, 0.0, z));
+            assert!(
+                coordinates.iter().all(|c| c.is_finite()),
+                "{:?}",
+                coordinates
+            );
+        }
+        let ray = Ray::new(Vector3::new(0.5, 1.0, 0.5), Vector3::new(0.0, -1.0, 0.0));
+        let collision = cuboid.collide(&ray, 0.0, 10.0).unwrap();
+        assert!((collision.dist_from_origin() - 1.0).abs() < 1e-9);
+    }
+}
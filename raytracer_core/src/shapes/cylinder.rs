@@ -0,0 +1,180 @@
+use std::borrow::Borrow;
+use std::f64::consts::PI;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Cylinder closed by two caps, going up along y from the center of its bottom cap `base`.
+///
+/// On the side, the first texture coordinate goes around the axis like on a `Sphere` and the second one
+/// goes from 0 at the bottom to 1 at the top. The caps are fitted in the square of texture coordinates 0 to 1.
+pub struct Cylinder {
+    base: Vector3<f64>,
+    radius: f64,
+    height: f64,
+    material: Box<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(
+        base: Vector3<f64>,
+        radius: f64,
+        height: f64,
+        material: Box<dyn Material>,
+    ) -> Cylinder {
+        Cylinder {
+            base,
+            radius,
+            height,
+            material,
+        }
+    }
+
+    /// Whether `local`, relative to `base`, is closer to a cap than to the side.
+    fn is_on_cap(&self, local: &Vector3<f64>) -> bool {
+        let cap_distance = local.y.abs().min((local.y - self.height).abs());
+        let side_distance = (local.xz().magnitude() - self.radius).abs();
+        cap_distance < side_distance
+    }
+}
+
+impl Shape for Cylinder {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let origin = ray.origin() - self.base;
+        let direction = ray.direction();
+        let mut nearest = None;
+        let mut keep_nearest = |t: f64| {
+            if t > t_min && t < nearest.unwrap_or(t_max) {
+                nearest = Some(t);
+            }
+        };
+
+        let a = direction.xz().magnitude_squared();
+        if a > 1e-12 {
+            let half_b = origin.xz().dot(&direction.xz());
+            let c = origin.xz().magnitude_squared() - self.radius * self.radius;
+            let discriminant = half_b * half_b - a * c;
+            if discriminant > 0.0 {
+                let root = discriminant.sqrt();
+                for &t in &[(-half_b - root) / a, (-half_b + root) / a] {
+                    let y = origin.y + t * direction.y;
+                    if y >= 0.0 && y <= self.height {
+                        keep_nearest(t);
+                    }
+                }
+            }
+        }
+        if direction.y.abs() > 1e-12 {
+            for &cap in &[0.0, self.height] {
+                let t = (cap - origin.y) / direction.y;
+                if (origin + direction * t).xz().magnitude_squared() <= self.radius * self.radius {
+                    keep_nearest(t);
+                }
+            }
+        }
+
+        let hit_distance_from_ray_origin = nearest?;
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            ray.at(hit_distance_from_ray_origin),
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let local = position - self.base;
+        if self.is_on_cap(&local) {
+            let up = local.y > self.height * 0.5;
+            Vector3::new(0.0, if up { 1.0 } else { -1.0 }, 0.0)
+        } else {
+            Vector3::new(local.x, 0.0, local.z) / self.radius
+        }
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let local = position - self.base;
+        if self.is_on_cap(&local) {
+            Vector2::new(
+                (1.0 + local.x / self.radius) * 0.5,
+                (1.0 + local.z / self.radius) * 0.5,
+            )
+        } else {
+            Vector2::new(
+                (1.0 + local.z.atan2(local.x) / PI) * 0.5,
+                local.y / self.height,
+            )
+        }
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let local = position - self.base;
+        if self.is_on_cap(&local) {
+            Vector3::new(1.0, 0.0, 0.0)
+        } else {
+            Vector3::new(-local.z, 0.0, local.x) / self.radius
+        }
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        let radius = self.radius.abs();
+        Some(AABB::new(
+            self.base + Vector3::new(-radius, 0.0, -radius),
+            self.base + Vector3::new(radius, self.height, radius),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(Vector3::zeros(), 1.0, 2.0, Box::new(Lambertian::new(0.5)))
+    }
+
+    fn hit(origin: Vector3<f64>, direction: Vector3<f64>) -> Option<(f64, Vector3<f64>)> {
+        let cylinder = cylinder();
+        let collision = cylinder.collide(&Ray::new(origin, direction), 0.0, 100.0)?;
+        Some((collision.dist_from_origin(), collision.normal()))
+    }
+
+    #[test]
+    fn hits_the_side() {
+        let (t, normal) = hit(Vector3::new(5.0, 1.0, 0.0), Vector3::new(-1.0, 0.0, 0.0)).unwrap();
+        assert!((t - 4.0).abs() < 1e-9);
+        assert!((normal - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn hits_the_caps() {
+        let (t, normal) = hit(Vector3::new(0.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((t - 3.0).abs() < 1e-9);
+        assert!((normal - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+        let (t, normal) = hit(Vector3::new(0.5, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((t - 5.0).abs() < 1e-9);
+        assert!((normal - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn exits_from_inside() {
+        let (t, _) = hit(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((t - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn misses_above_and_beside() {
+        assert!(hit(Vector3::new(5.0, 3.0, 0.0), Vector3::new(-1.0, 0.0, 0.0)).is_none());
+        assert!(hit(Vector3::new(5.0, 1.0, 1.5), Vector3::new(-1.0, 0.0, 0.0)).is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::f64::consts::{PI, TAU};
+
+use nalgebra::{Vector2, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::Ray;
+use crate::world::T_MIN;
+
+use super::shape::Shape;
+
+/// Flat disk facing `normal`, the square of texture coordinates 0 to 1 is fitted around it.
+pub struct Disk {
+    center: Vector3<f64>,
+    normal: Vector3<f64>,
+    tangent: Vector3<f64>,
+    bitangent: Vector3<f64>,
+    radius: f64,
+    material: Box<dyn Material>,
+    rng: RefCell<SmallRng>,
+}
+
+impl Disk {
+    pub fn new(
+        center: Vector3<f64>,
+        normal: Vector3<f64>,
+        radius: f64,
+        material: Box<dyn Material>,
+    ) -> Disk {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = orthonormal_basis(&normal);
+        Disk {
+            center,
+            normal,
+            tangent,
+            bitangent,
+            radius,
+            material,
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+}
+
+impl Shape for Disk {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let hit_distance_from_ray_origin =
+            (self.center - ray.origin()).dot(&self.normal) / denominator;
+        if hit_distance_from_ray_origin >= t_max || hit_distance_from_ray_origin <= t_min {
+            return None;
+        }
+        let collision_origin = ray.at(hit_distance_from_ray_origin);
+        if (collision_origin - self.center).magnitude_squared() > self.radius * self.radius {
+            return None;
+        }
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            collision_origin,
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        self.normal
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let offset = (position - self.center) / self.radius;
+        Vector2::new(
+            (1.0 + offset.dot(&self.tangent)) * 0.5,
+            (1.0 + offset.dot(&self.bitangent)) * 0.5,
+        )
+    }
+
+    fn tangent_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        self.tangent
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        // Extent of the disk along each axis, the radius times the sine of the angle with the normal.
+        let extent = self
+            .normal
+            .map(|n| self.radius * (1.0 - n * n).max(0.0).sqrt());
+        // Axis aligned disks would give flat boxes.
+        Some(AABB::new(self.center - extent, self.center + extent).expand(1e-4))
+    }
+
+    fn pdf_value(&self, origin: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        match self.collide(&Ray::new(*origin, *direction), T_MIN, f64::INFINITY) {
+            Some(collision) => {
+                // Uniform on the area, converted to solid angle.
+                let distance_squared =
+                    collision.dist_from_origin().powi(2) * direction.magnitude_squared();
+                let cosine = (direction.dot(&self.normal) / direction.magnitude()).abs();
+                distance_squared / (cosine * PI * self.radius * self.radius)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Vector3<f64>) -> Option<Vector3<f64>> {
+        let mut rng = self.rng.borrow_mut();
+        let radius = self.radius * rng.gen_range(0.0f64, 1.0).sqrt();
+        let angle = TAU * rng.gen_range(0.0, 1.0);
+        let point = self.center
+            + self.tangent * (radius * angle.cos())
+            + self.bitangent * (radius * angle.sin());
+        Some(point - origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    /// Disk of radius 2 centered at (0, 1, 0) facing +y.
+    fn disk() -> Disk {
+        Disk::new(
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 3.0, 0.0),
+            2.0,
+            Box::new(Lambertian::new(0.5)),
+        )
+    }
+
+    #[test]
+    fn hits_inside_the_radius() {
+        let disk = disk();
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        let collision = disk
+            .collide(&Ray::new(Vector3::new(0.0, 5.0, 0.0), down), 0.0, 10.0)
+            .unwrap();
+        assert!((collision.dist_from_origin() - 4.0).abs() < 1e-9);
+        assert!((collision.texture_coordinates() - Vector2::new(0.5, 0.5)).magnitude() < 1e-9);
+        assert_eq!(collision.normal(), Vector3::new(0.0, 1.0, 0.0));
+        assert!(disk
+            .collide(&Ray::new(Vector3::new(1.9, 5.0, 0.0), down), 0.0, 10.0)
+            .is_some());
+        assert!(disk
+            .collide(&Ray::new(Vector3::new(1.5, 5.0, 1.5), down), 0.0, 10.0)
+            .is_none());
+        let parallel = Ray::new(Vector3::new(-5.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(disk.collide(&parallel, 0.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn sampled_directions_hit_the_disk() {
+        let disk = disk();
+        let origin = Vector3::new(1.0, 4.0, -1.0);
+        for _ in 0..100 {
+            let direction = disk.random_direction(&origin).unwrap();
+            let point = origin + direction;
+            assert!((point.y - 1.0).abs() < 1e-9);
+            assert!((point - Vector3::new(0.0, 1.0, 0.0)).magnitude() <= 2.0 + 1e-9);
+            assert!(disk.pdf_value(&origin, &direction) > 0.0);
+        }
+        assert_eq!(disk.pdf_value(&origin, &Vector3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+}
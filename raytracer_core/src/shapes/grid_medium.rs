@@ -0,0 +1,153 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+
+use nalgebra::{Vector2, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::Material;
+use crate::materials::phase_function::PhaseFunction;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+use crate::shapes::voxel_grid::VoxelGrid;
+
+use super::shape::Shape;
+
+/// Heterogeneous volume whose density is read from a voxel grid, like smoke simulations or scans.
+///
+/// Collisions are found with delta (Woodcock) tracking and shadow rays are attenuated with ratio tracking,
+/// both against the largest density of the grid.
+pub struct GridMedium {
+    grid: VoxelGrid,
+    density_scale: f64,
+    // upper bound of the density, the rate of the fictitious collisions of the trackers
+    majorant: f64,
+    phase_function: Box<dyn Material>,
+    rng: RefCell<SmallRng>,
+}
+
+impl GridMedium {
+    /// The density at a point is the value of the grid times `density_scale`, which must be non negative.
+    pub fn new(grid: VoxelGrid, density_scale: f64, phase_function: PhaseFunction) -> GridMedium {
+        assert!(
+            density_scale >= 0.0 && density_scale.is_finite(),
+            "the density scale must be finite and non negative"
+        );
+        GridMedium {
+            majorant: grid.max_value() * density_scale,
+            grid,
+            density_scale,
+            phase_function: Box::new(phase_function),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    fn density(&self, position: &Vector3<f64>) -> f64 {
+        self.grid.value_at(position) * self.density_scale
+    }
+
+    /// Distances along the ray of the tentative collisions between `t_min` and `t_max`,
+    /// drawn as if the whole grid had the majorant density.
+    fn tentative_collisions<'a>(
+        &'a self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> impl Iterator<Item = f64> + 'a {
+        let interval = if self.majorant > 0.0 {
+            self.grid.bounds().intersection(ray, t_min, t_max)
+        } else {
+            None
+        };
+        let ray_length = ray.direction().magnitude();
+        let mut t = interval.map_or(f64::INFINITY, |(enter, _)| enter);
+        let exit = interval.map_or(f64::NEG_INFINITY, |(_, exit)| exit);
+        std::iter::from_fn(move || {
+            let u: f64 = self.rng.borrow_mut().gen();
+            t += -(1.0 - u).ln() / (self.majorant * ray_length);
+            if t < exit {
+                Some(t)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Shape for GridMedium {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        for t in self.tentative_collisions(ray, t_min, t_max) {
+            let position = ray.at(t);
+            // Real collision with a probability of density / majorant, otherwise a fictitious one.
+            if self.rng.borrow_mut().gen::<f64>() * self.majorant < self.density(&position) {
+                return Some(Collision::new(t, position, self));
+            }
+        }
+        None
+    }
+
+    /// Volumes have no surface, any unit vector will do.
+    fn normal_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+
+    fn texture_coords_at_position(&self, _position: &Vector3<f64>) -> Vector2<f64> {
+        Vector2::zeros()
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.phase_function.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        Some(*self.grid.bounds())
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        Some(
+            self.tentative_collisions(ray, t_min, t_max)
+                .map(|t| 1.0 - self.density(&ray.at(t)) / self.majorant)
+                .product(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> VoxelGrid {
+        VoxelGrid::new(
+            [2, 1, 1],
+            AABB::new(Vector3::zeros(), Vector3::repeat(1.0)),
+            vec![0.0, 4.0],
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "density scale")]
+    fn rejects_negative_density_scale() {
+        GridMedium::new(grid(), -1.0, PhaseFunction::isotropic(Vector3::repeat(1.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "non negative")]
+    fn rejects_negative_voxels() {
+        VoxelGrid::new(
+            [1, 1, 1],
+            AABB::new(Vector3::zeros(), Vector3::repeat(1.0)),
+            vec![-1.0],
+        );
+    }
+
+    #[test]
+    fn transmittance_stays_between_zero_and_one() {
+        let medium = GridMedium::new(grid(), 1.0, PhaseFunction::isotropic(Vector3::repeat(1.0)));
+        let ray = Ray::new(Vector3::new(-1.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        for _ in 0..100 {
+            let transmittance = medium.transmittance(&ray, 0.0, 10.0).unwrap();
+            assert!((0.0..=1.0).contains(&transmittance), "{}", transmittance);
+        }
+    }
+}
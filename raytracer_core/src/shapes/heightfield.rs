@@ -0,0 +1,321 @@
+use std::borrow::Borrow;
+use std::path::Path;
+
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::ImageResult;
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+use crate::shapes::triangle;
+
+use super::shape::Shape;
+
+/// Terrain made of a grid of heights, each cell of the grid split in two triangles.
+///
+/// The grid spans `bounds` along x and z, heights go from the bottom of `bounds` for 0
+/// to its top for 1. Texture coordinates go from 0 to 1 over the whole terrain.
+///
+/// Rays walk the grid cell by cell and only test the triangles of the cells whose height range
+/// they cross. There is no hierarchy over the cells: a walk is linear in the number of cells crossed,
+/// which stays low unless grazing rays cross a whole large terrain.
+pub struct Heightfield {
+    // samples along x and z
+    resolution: [usize; 2],
+    bounds: AABB,
+    // size of a cell along x and z
+    cell_size: Vector2<f64>,
+    // row by row, x varying first
+    heights: Vec<f64>,
+    normals: Vec<Vector3<f64>>,
+    // lowest and highest points of each cell, row by row
+    cell_ranges: Vec<(f64, f64)>,
+    material: Box<dyn Material>,
+}
+
+impl Heightfield {
+    /// `heights` holds `resolution[0] * resolution[1]` values from 0 to 1, x varying first.
+    /// Panics with less than 2 samples along an axis, if the number of heights doesn't match
+    /// or if a height is out of that range.
+    pub fn new(
+        resolution: [usize; 2],
+        bounds: AABB,
+        heights: Vec<f64>,
+        material: Box<dyn Material>,
+    ) -> Heightfield {
+        assert!(
+            resolution[0] >= 2 && resolution[1] >= 2,
+            "a heightfield needs at least 2 samples along each axis"
+        );
+        assert_eq!(
+            heights.len(),
+            resolution[0] * resolution[1],
+            "number of heights doesn't match the resolution"
+        );
+        assert!(
+            heights.iter().all(|height| (0.0..=1.0).contains(height)),
+            "heights must be between 0 and 1"
+        );
+        let extent = bounds.extent();
+        let mut heightfield = Heightfield {
+            resolution,
+            bounds,
+            cell_size: Vector2::new(
+                extent.x / (resolution[0] - 1) as f64,
+                extent.z / (resolution[1] - 1) as f64,
+            ),
+            heights,
+            normals: Vec::new(),
+            cell_ranges: Vec::new(),
+            material,
+        };
+        heightfield.normals = (0..resolution[1])
+            .flat_map(|z| (0..resolution[0]).map(move |x| (x, z)))
+            .map(|(x, z)| heightfield.vertex_normal(x, z))
+            .collect();
+        heightfield.cell_ranges = (0..resolution[1] - 1)
+            .flat_map(|z| (0..resolution[0] - 1).map(move |x| [x, z]))
+            .map(|cell| heightfield.compute_cell_range(cell))
+            .collect();
+        heightfield
+    }
+
+    /// Heights from the brightness of a grayscale image, one sample per pixel,
+    /// the top of the image is at the lowest z. Fails for images less than 2 pixels wide or tall.
+    pub fn load_from_file(
+        path: &Path,
+        bounds: AABB,
+        material: Box<dyn Material>,
+    ) -> ImageResult<Heightfield> {
+        let image = image::open(path)?.to_luma16();
+        if image.width() < 2 || image.height() < 2 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic(
+                    "a heightfield needs at least 2 pixels along each axis".to_string(),
+                ),
+            )));
+        }
+        let heights = image
+            .pixels()
+            .map(|pixel| pixel[0] as f64 / u16::MAX as f64)
+            .collect();
+        Ok(Heightfield::new(
+            [image.width() as usize, image.height() as usize],
+            bounds,
+            heights,
+            material,
+        ))
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Vector3<f64> {
+        let min = self.bounds.min();
+        Vector3::new(
+            min.x + x as f64 * self.cell_size.x,
+            min.y + self.heights[z * self.resolution[0] + x] * self.bounds.extent().y,
+            min.z + z as f64 * self.cell_size.y,
+        )
+    }
+
+    /// Normal of the terrain at a sample, from the slopes toward its neighbours.
+    fn vertex_normal(&self, x: usize, z: usize) -> Vector3<f64> {
+        let slope = |before: Vector3<f64>, after: Vector3<f64>| {
+            let difference = after - before;
+            difference.y / (difference.x + difference.z)
+        };
+        let [width, depth] = self.resolution;
+        let slope_x = slope(
+            self.vertex(x.saturating_sub(1), z),
+            self.vertex((x + 1).min(width - 1), z),
+        );
+        let slope_z = slope(
+            self.vertex(x, z.saturating_sub(1)),
+            self.vertex(x, (z + 1).min(depth - 1)),
+        );
+        Vector3::new(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    /// Cell containing `position`, and where `position` is in the cell from 0 to 1 along x and z.
+    fn cell_at(&self, position: &Vector3<f64>) -> ([usize; 2], Vector2<f64>) {
+        let grid = (position - self.bounds.min())
+            .xz()
+            .component_div(&self.cell_size);
+        let x = (grid.x.floor().max(0.0) as usize).min(self.resolution[0] - 2);
+        let z = (grid.y.floor().max(0.0) as usize).min(self.resolution[1] - 2);
+        ([x, z], grid - Vector2::new(x as f64, z as f64))
+    }
+
+    /// Nearest hit of the ray with the two triangles of a cell.
+    fn collide_cell(&self, ray: &Ray, cell: [usize; 2], t_min: f64, t_max: f64) -> Option<f64> {
+        let [x, z] = cell;
+        let corners = [
+            self.vertex(x, z),
+            self.vertex(x + 1, z),
+            self.vertex(x + 1, z + 1),
+            self.vertex(x, z + 1),
+        ];
+        let first = triangle::intersect(ray, &corners[0], &corners[1], &corners[2], t_min, t_max);
+        let t_max = first.unwrap_or(t_max);
+        triangle::intersect(ray, &corners[0], &corners[2], &corners[3], t_min, t_max).or(first)
+    }
+
+    /// Lowest and highest points of a cell.
+    fn cell_range(&self, cell: [usize; 2]) -> (f64, f64) {
+        self.cell_ranges[cell[1] * (self.resolution[0] - 1) + cell[0]]
+    }
+
+    fn compute_cell_range(&self, cell: [usize; 2]) -> (f64, f64) {
+        let [x, z] = cell;
+        [(x, z), (x + 1, z), (x, z + 1), (x + 1, z + 1)]
+            .iter()
+            .map(|&(x, z)| self.vertex(x, z).y)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), y| {
+                (low.min(y), high.max(y))
+            })
+    }
+}
+
+impl Shape for Heightfield {
+    /// Walks the cells crossed by the ray over the grid, from the nearest one, skipping the cells
+    /// the ray passes above or below.
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let (start, end) = self.bounds.expand(1e-4).intersection(ray, t_min, t_max)?;
+        let direction = ray.direction();
+        let cell_size = self.cell_size;
+        let (mut cell, offset) = self.cell_at(&ray.at(start));
+
+        // Distance along the ray to the next cell boundary and between boundaries, along x then z.
+        let mut next = [f64::INFINITY; 2];
+        let mut delta = [f64::INFINITY; 2];
+        let mut step = [0isize; 2];
+        for axis in 0..2 {
+            let speed = if axis == 0 { direction.x } else { direction.z };
+            if speed.abs() < 1e-12 {
+                continue;
+            }
+            delta[axis] = cell_size[axis] / speed.abs();
+            let to_boundary = if speed > 0.0 {
+                1.0 - offset[axis]
+            } else {
+                offset[axis]
+            };
+            next[axis] = start + to_boundary * delta[axis];
+            step[axis] = if speed > 0.0 { 1 } else { -1 };
+        }
+
+        let mut enter = start;
+        while enter <= end {
+            let exit = next[0].min(next[1]).min(end);
+            let (low, high) = self.cell_range(cell);
+            let (y_enter, y_exit) = (ray.at(enter).y, ray.at(exit).y);
+            if y_enter.min(y_exit) <= high && y_enter.max(y_exit) >= low {
+                if let Some(t) = self.collide_cell(ray, cell, t_min, t_max) {
+                    return Some(Collision::new(t, ray.at(t), self));
+                }
+            }
+            let axis = if next[0] < next[1] { 0 } else { 1 };
+            let index = cell[axis] as isize + step[axis];
+            if index < 0 || index > self.resolution[axis] as isize - 2 {
+                return None;
+            }
+            cell[axis] = index as usize;
+            enter = next[axis];
+            next[axis] += delta[axis];
+        }
+        None
+    }
+
+    /// Normals of the corners of the cell, blended.
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let ([x, z], offset) = self.cell_at(position);
+        let (u, v) = (offset.x.clamp(0.0, 1.0), offset.y.clamp(0.0, 1.0));
+        let width = self.resolution[0];
+        let normal = |x: usize, z: usize| self.normals[z * width + x];
+        let normal = (normal(x, z) * (1.0 - u) + normal(x + 1, z) * u) * (1.0 - v)
+            + (normal(x, z + 1) * (1.0 - u) + normal(x + 1, z + 1) * u) * v;
+        normal.normalize()
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        (position - self.bounds.min())
+            .xz()
+            .component_div(&self.bounds.extent().xz())
+    }
+
+    fn tangent_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(1.0, 0.0, 0.0)
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        Some(self.bounds.expand(1e-4))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn heightfield() -> Heightfield {
+        let resolution = [9, 7];
+        let heights = (0..resolution[0] * resolution[1])
+            .map(|i| (i * 37 % 11) as f64 / 10.0)
+            .collect();
+        let bounds = AABB::new(Vector3::new(-2.0, 0.0, -1.0), Vector3::new(2.0, 1.0, 2.0));
+        Heightfield::new(resolution, bounds, heights, Box::new(Lambertian::new(0.5)))
+    }
+
+    #[test]
+    fn walk_finds_the_same_hits_as_every_cell() {
+        let heightfield = heightfield();
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..2000 {
+            let origin = Vector3::new(
+                rng.gen_range(-3.0, 3.0),
+                rng.gen_range(-0.5, 2.0),
+                rng.gen_range(-2.0, 3.0),
+            );
+            let target = Vector3::new(
+                rng.gen_range(-2.0, 2.0),
+                rng.gen_range(0.0, 1.0),
+                rng.gen_range(-1.0, 2.0),
+            );
+            let ray = Ray::new(origin, target - origin);
+            let expected = (0..heightfield.resolution[1] - 1)
+                .flat_map(|z| (0..heightfield.resolution[0] - 1).map(move |x| [x, z]))
+                .filter_map(|cell| heightfield.collide_cell(&ray, cell, 0.001, 100.0))
+                .fold(f64::INFINITY, f64::min);
+            match heightfield.collide(&ray, 0.001, 100.0) {
+                Some(collision) => assert!((collision.dist_from_origin() - expected).abs() < 1e-9),
+                None => assert!(expected.is_infinite(), "missed a hit at {}", expected),
+            }
+        }
+    }
+
+    #[test]
+    fn single_pixel_rows_are_an_error() {
+        let path =
+            std::env::temp_dir().join(format!("raytracer_heightfield_{}.png", std::process::id()));
+        image::GrayImage::new(1, 4).save(&path).unwrap();
+        let bounds = AABB::new(Vector3::zeros(), Vector3::repeat(1.0));
+        let heightfield =
+            Heightfield::load_from_file(&path, bounds, Box::new(Lambertian::new(0.5)));
+        std::fs::remove_file(&path).unwrap();
+        assert!(heightfield.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "heights must be between 0 and 1")]
+    fn heights_out_of_range_are_rejected() {
+        let bounds = AABB::new(Vector3::zeros(), Vector3::repeat(1.0));
+        let heights = vec![0.0, 0.5, f64::NAN, 1.0];
+        Heightfield::new([2, 2], bounds, heights, Box::new(Lambertian::new(0.5)));
+    }
+}
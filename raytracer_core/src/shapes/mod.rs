@@ -1,4 +1,24 @@
+pub mod aabb;
+pub mod animated;
+pub mod bvh;
 pub mod collision;
+pub mod cone;
+pub mod constant_medium;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod grid_medium;
+pub mod heightfield;
+pub mod moving_sphere;
+pub mod plane;
+pub mod quad;
 pub mod ray;
+pub mod sdf;
 pub mod shape;
 pub mod sphere;
+pub mod torus;
+pub mod transformed;
+pub mod triangle;
+pub mod triangle_mesh;
+pub mod voxel_grid;
@@ -0,0 +1,108 @@
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+use crate::shapes::sphere::Sphere;
+
+use super::shape::Shape;
+
+/// Sphere going in a straight line from `center0` at `time0` to `center1` at `time1`,
+/// it stays at the ends before and after.
+pub struct MovingSphere {
+    // same sphere centered on the origin, moved to where it is at the time of each ray
+    sphere: Sphere,
+    center0: Vector3<f64>,
+    center1: Vector3<f64>,
+    time0: f64,
+    time1: f64,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Vector3<f64>,
+        time0: f64,
+        center1: Vector3<f64>,
+        time1: f64,
+        radius: f64,
+        material: Box<dyn Material>,
+    ) -> MovingSphere {
+        MovingSphere {
+            sphere: Sphere::new(Vector3::zeros(), radius, material),
+            center0,
+            center1,
+            time0,
+            time1,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Vector3<f64> {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let fraction = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + (self.center1 - self.center0) * fraction
+    }
+}
+
+impl Shape for MovingSphere {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let center = self.center(ray.time());
+        let local_ray = Ray::new(ray.origin() - center, *ray.direction())
+            .with_spread(ray.spread())
+            .with_time(ray.time());
+        let collision = self.sphere.collide(&local_ray, t_min, t_max)?;
+        // The collision keeps where the sphere was for its normal and texture coordinates.
+        Some(collision.translated(&center))
+    }
+
+    /// Normal of the sphere at `time0`.
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        self.sphere
+            .normal_at_position(&(position - self.center(self.time0)))
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        self.sphere
+            .texture_coords_at_position(&(position - self.center(self.time0)))
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.sphere.material()
+    }
+
+    /// Box containing the sphere all along its way.
+    fn bounding_box(&self) -> Option<AABB> {
+        let bounds = self.sphere.bounding_box()?;
+        let at = |center: &Vector3<f64>| AABB::new(bounds.min() + center, bounds.max() + center);
+        Some(at(&self.center0).surrounding(&at(&self.center1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    #[test]
+    fn collides_where_the_sphere_is_at_the_ray_time() {
+        let sphere = MovingSphere::new(
+            Vector3::zeros(),
+            0.0,
+            Vector3::new(0.0, 0.0, -2.0),
+            1.0,
+            1.0,
+            Box::new(Lambertian::new(0.5)),
+        );
+        for &(time, expected) in [(-1.0, 4.0), (0.5, 5.0), (2.0, 6.0)].iter() {
+            let ray =
+                Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0)).with_time(time);
+            let collision = sphere.collide(&ray, 0.0, 100.0).unwrap();
+            assert!((collision.dist_from_origin() - expected).abs() < 1e-9);
+            assert!((collision.position() - ray.at(expected)).magnitude() < 1e-9);
+            let normal = collision.normal();
+            assert!((normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::borrow::Borrow;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Infinite plane going through `point`, texture coordinates are the distances along the plane axes
+/// so the texture repeats every unit.
+pub struct Plane {
+    point: Vector3<f64>,
+    normal: Vector3<f64>,
+    tangent: Vector3<f64>,
+    bitangent: Vector3<f64>,
+    material: Box<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vector3<f64>, normal: Vector3<f64>, material: Box<dyn Material>) -> Plane {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = orthonormal_basis(&normal);
+        Plane {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+/// Returns two unit vectors orthogonal to `normal` and to each other.
+pub(crate) fn orthonormal_basis(normal: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vector3::new(0.0, 1.0, 0.0)
+    } else {
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+impl Shape for Plane {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let hit_distance_from_ray_origin =
+            (self.point - ray.origin()).dot(&self.normal) / denominator;
+        if hit_distance_from_ray_origin < t_max && hit_distance_from_ray_origin > t_min {
+            return Some(Collision::new(
+                hit_distance_from_ray_origin,
+                ray.at(hit_distance_from_ray_origin),
+                self,
+            ));
+        }
+        None
+    }
+
+    fn normal_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        self.normal
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let offset = position - self.point;
+        Vector2::new(offset.dot(&self.tangent), offset.dot(&self.bitangent))
+    }
+
+    fn tangent_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        self.tangent
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        None
+    }
+}
@@ -0,0 +1,225 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+
+use nalgebra::{Vector2, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+use crate::world::T_MIN;
+
+use super::shape::Shape;
+
+/// Parallelogram with a corner at `origin` and sides `u` and `v`.
+/// The normal is `u × v`, texture coordinates go from (0, 0) at `origin` to (1, 1) at the opposite corner.
+pub struct Quad {
+    origin: Vector3<f64>,
+    u: Vector3<f64>,
+    v: Vector3<f64>,
+    normal: Vector3<f64>,
+    // u × v / |u × v|², used to get planar coordinates of a point
+    w: Vector3<f64>,
+    area: f64,
+    material: Box<dyn Material>,
+    rng: RefCell<SmallRng>,
+}
+
+impl Quad {
+    pub fn new(
+        origin: Vector3<f64>,
+        u: Vector3<f64>,
+        v: Vector3<f64>,
+        material: Box<dyn Material>,
+    ) -> Quad {
+        let n = u.cross(&v);
+        Quad {
+            origin,
+            u,
+            v,
+            normal: n.normalize(),
+            w: n / n.magnitude_squared(),
+            area: n.magnitude(),
+            material,
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// Rectangle in the plane z = `z`, facing +z.
+    pub fn new_xy(x: (f64, f64), y: (f64, f64), z: f64, material: Box<dyn Material>) -> Quad {
+        Quad::new(
+            Vector3::new(x.0, y.0, z),
+            Vector3::new(x.1 - x.0, 0.0, 0.0),
+            Vector3::new(0.0, y.1 - y.0, 0.0),
+            material,
+        )
+    }
+
+    /// Rectangle in the plane y = `y`, facing +y.
+    pub fn new_xz(x: (f64, f64), z: (f64, f64), y: f64, material: Box<dyn Material>) -> Quad {
+        Quad::new(
+            Vector3::new(x.0, y, z.0),
+            Vector3::new(0.0, 0.0, z.1 - z.0),
+            Vector3::new(x.1 - x.0, 0.0, 0.0),
+            material,
+        )
+    }
+
+    /// Rectangle in the plane x = `x`, facing +x.
+    pub fn new_yz(y: (f64, f64), z: (f64, f64), x: f64, material: Box<dyn Material>) -> Quad {
+        Quad::new(
+            Vector3::new(x, y.0, z.0),
+            Vector3::new(0.0, y.1 - y.0, 0.0),
+            Vector3::new(0.0, 0.0, z.1 - z.0),
+            material,
+        )
+    }
+
+    pub fn origin(&self) -> &Vector3<f64> {
+        &self.origin
+    }
+
+    pub fn u(&self) -> &Vector3<f64> {
+        &self.u
+    }
+
+    pub fn v(&self) -> &Vector3<f64> {
+        &self.v
+    }
+
+    fn planar_coordinates(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let offset = position - self.origin;
+        Vector2::new(
+            self.w.dot(&offset.cross(&self.v)),
+            self.w.dot(&self.u.cross(&offset)),
+        )
+    }
+}
+
+impl Shape for Quad {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let hit_distance_from_ray_origin =
+            (self.origin - ray.origin()).dot(&self.normal) / denominator;
+        if hit_distance_from_ray_origin >= t_max || hit_distance_from_ray_origin <= t_min {
+            return None;
+        }
+        let collision_origin = ray.at(hit_distance_from_ray_origin);
+        let coordinates = self.planar_coordinates(&collision_origin);
+        if coordinates.x < 0.0 || coordinates.x > 1.0 || coordinates.y < 0.0 || coordinates.y > 1.0
+        {
+            return None;
+        }
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            collision_origin,
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        self.normal
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        self.planar_coordinates(position)
+    }
+
+    fn tangent_at_position(&self, _position: &Vector3<f64>) -> Vector3<f64> {
+        self.u.normalize()
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        let corners = [
+            self.origin + self.u,
+            self.origin + self.v,
+            self.origin + self.u + self.v,
+        ];
+        let bounds = corners
+            .iter()
+            .fold(AABB::new(self.origin, self.origin), |bounds, corner| {
+                bounds.surrounding(&AABB::new(*corner, *corner))
+            });
+        // Axis aligned quads would give flat boxes.
+        Some(bounds.expand(1e-4))
+    }
+
+    fn pdf_value(&self, origin: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        match self.collide(&Ray::new(*origin, *direction), T_MIN, f64::INFINITY) {
+            Some(collision) => {
+                // Uniform on the area, converted to solid angle.
+                let distance_squared =
+                    collision.dist_from_origin().powi(2) * direction.magnitude_squared();
+                let cosine = (direction.dot(&self.normal) / direction.magnitude()).abs();
+                distance_squared / (cosine * self.area)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Vector3<f64>) -> Option<Vector3<f64>> {
+        let mut rng = self.rng.borrow_mut();
+        let point =
+            self.origin + self.u * rng.gen_range(0.0, 1.0) + self.v * rng.gen_range(0.0, 1.0);
+        Some(point - origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    /// Slanted quad with a corner at (1, 0, 0), sides along y and along x + z.
+    fn quad() -> Quad {
+        Quad::new(
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Box::new(Lambertian::new(0.5)),
+        )
+    }
+
+    #[test]
+    fn hits_inside_with_planar_coordinates() {
+        let quad = quad();
+        let ray = Ray::new(Vector3::new(5.0, 1.0, 0.5), Vector3::new(-1.0, 0.0, 0.0));
+        let collision = quad.collide(&ray, 0.0, 100.0).unwrap();
+        assert!((collision.dist_from_origin() - 3.5).abs() < 1e-9);
+        let coordinates = collision.texture_coordinates();
+        assert!((coordinates - Vector2::new(0.5, 0.5)).magnitude() < 1e-9);
+        let expected_normal = Vector3::new(1.0, 0.0, -1.0).normalize();
+        assert!((collision.normal() - expected_normal).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn misses_outside_and_parallel() {
+        let quad = quad();
+        let direction = Vector3::new(-1.0, 0.0, 0.0);
+        assert!(quad
+            .collide(
+                &Ray::new(Vector3::new(5.0, 2.5, 0.5), direction),
+                0.0,
+                100.0
+            )
+            .is_none());
+        assert!(quad
+            .collide(
+                &Ray::new(Vector3::new(5.0, 1.0, 1.5), direction),
+                0.0,
+                100.0
+            )
+            .is_none());
+        let parallel = Ray::new(Vector3::new(0.0, 1.0, -1.0), Vector3::new(1.0, 0.0, 1.0));
+        assert!(quad.collide(&parallel, 0.0, 100.0).is_none());
+    }
+}
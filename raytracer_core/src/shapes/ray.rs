@@ -1,10 +1,7 @@
 use crate::shapes::collision::Collision;
-use crate::shapes::shape::Shape;
+use crate::world::World;
 use nalgebra::Vector3;
 
-const T_MIN: f64 = 0.001;
-const T_MAX: f64 = 100_000.0;
-
 pub type Color = Vector3<f64>;
 
 trait Blendable {
@@ -20,11 +17,30 @@ impl Blendable for Color {
 pub struct Ray {
     origin: Vector3<f64>,
     direction: Vector3<f64>,
+    spread: f64,
+    time: f64,
 }
 
 impl Ray {
     pub fn new(origin: Vector3<f64>, direction: Vector3<f64>) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            spread: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Ray standing for a cone of `spread` radians, like the part of the scene seen by a pixel.
+    pub fn with_spread(mut self, spread: f64) -> Ray {
+        self.spread = spread;
+        self
+    }
+
+    /// Instant the ray is traced at, moving shapes are where they are at that time.
+    pub fn with_time(mut self, time: f64) -> Ray {
+        self.time = time;
+        self
     }
 
     pub fn at(&self, t: f64) -> Vector3<f64> {
@@ -39,56 +55,158 @@ impl Ray {
         &self.direction
     }
 
-    pub fn project_ray(&self, scene: &[Box<dyn Shape>]) -> Color {
+    /// Angle of the cone around the ray, 0 for rays that aren't traced from the camera.
+    pub fn spread(&self) -> f64 {
+        self.spread
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn project_ray(&self, world: &World) -> Color {
         // parameterize max depth
-        self._project_ray(scene, 50)
-    }
-    
-    pub(crate) fn find_collision<'a: 'b, 'b>(&'a self, scene: &'a [Box<dyn Shape>]) -> Option<(Collision<'a>, usize)> {
-        let mut maybe_collision: Option<(Collision, usize)> = None;
-        let mut index = 0;
-
-        for shape in scene {
-            let maybe_new_collision = shape.collide(self, T_MIN, T_MAX);
-
-            if let Some(new_collision) = maybe_new_collision {
-                match &maybe_collision {
-                    Some(old_collision)
-                        if old_collision.0.dist_from_origin()
-                            < new_collision.dist_from_origin() => {}
-                    _ => {
-                        maybe_collision = Some((new_collision, index));
-                    }
-                }
-            };
-            index += 1;
-        }
-        maybe_collision
+        self._project_ray(world, 50, None)
     }
 
-    fn _project_ray(&self, scene: &[Box<dyn Shape>], depth: i64) -> Color {
+    /// `bounce_pdf` is the probability density of the previous bounce choosing this ray,
+    /// `None` for camera rays and bounces that can't be compared with light sampling.
+    fn _project_ray(&self, world: &World, depth: i64, bounce_pdf: Option<f64>) -> Color {
         if depth == 0 {
-            return self.background_color();
+            return self.background_color(world);
         }
-        let may_collision = self.find_collision(scene);
+        let may_collision = world.find_collision(self);
 
         match may_collision {
-            Some(collision) => {
-                let new_color: Color = collision.0.color(self);
-                match collision.0.bounce(self) {
-                    Some(ray) => new_color.blend(&ray._project_ray(scene, depth - 1)),
-                    None => new_color,
+            Some((collision, index)) => {
+                let mut emitted = collision.emitted(self);
+                if let Some(bounce_pdf) = bounce_pdf {
+                    // The light was also sampled directly from the previous collision.
+                    let light_pdf = world.emitter_pdf(index, self.origin(), self.direction());
+                    emitted *= power_heuristic(bounce_pdf, light_pdf);
+                }
+                let direct_light = self.sample_emitter(world, &collision)
+                    + self.sample_lights(world, &collision)
+                    + self.sample_background(world, &collision);
+                match collision.sample(self) {
+                    Some(sample) => {
+                        let ray =
+                            Ray::new(*collision.position(), sample.direction).with_time(self.time);
+                        // Delta lobes can't be reached by light sampling, so there is nothing to weight against.
+                        let pdf = if sample.is_delta {
+                            None
+                        } else {
+                            Some(sample.pdf)
+                        };
+                        emitted
+                            + direct_light
+                            + sample
+                                .weight
+                                .blend(&ray._project_ray(world, depth - 1, pdf))
+                    }
+                    None => emitted + direct_light,
+                }
+            }
+            None => {
+                let color = self.background_color(world);
+                match bounce_pdf {
+                    // The background was also sampled directly from the previous collision.
+                    Some(bounce_pdf) => {
+                        let light_pdf = world.background().pdf_value(self.direction());
+                        color * power_heuristic(bounce_pdf, light_pdf)
+                    }
+                    None => color,
                 }
-                // eprintln!("{} + {} => {}", new_color, color_until_now, ret);
-                // handle recursion here 0.5 * diffusion_ray._project_ray(scene, depth - 1)
             }
-            None => self.background_color(),
         }
     }
 
-    fn background_color(&self) -> Color {
-        let t = 0.5 * (self.direction().normalize().y + 1.0);
+    /// Next event estimation: light coming straight from a randomly chosen emissive shape,
+    /// weighted against the chances of reaching it by bouncing.
+    fn sample_emitter(&self, world: &World, collision: &Collision) -> Color {
+        let light = world.sample_emitter(collision.position());
+        let (light_index, direction, light_pdf) = match light {
+            Some(light) => light,
+            None => return Color::zeros(),
+        };
+        let scattered = self.scattered(collision, &direction);
+        if scattered == Color::zeros() {
+            return Color::zeros();
+        }
+        let bounce_pdf = collision.pdf(self, &direction);
+        let shadow_ray = Ray::new(*collision.position(), direction).with_time(self.time);
+        match world.find_collision(&shadow_ray) {
+            Some((light_collision, index)) if index == light_index => {
+                let emitted = light_collision.emitted(&shadow_ray);
+                scattered.blend(&emitted) * power_heuristic(light_pdf, bounce_pdf) / light_pdf
+            }
+            _ => Color::zeros(),
+        }
+    }
 
-        Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+    /// Light coming from the analytic lights of the scene, they can't be reached by bouncing.
+    fn sample_lights(&self, world: &World, collision: &Collision) -> Color {
+        let mut light = Color::zeros();
+        for sample in world
+            .lights()
+            .iter()
+            .filter_map(|light| light.illuminate(collision.position()))
+        {
+            let scattered = self.scattered(collision, &sample.direction);
+            if scattered == Color::zeros() {
+                continue;
+            }
+            let shadow_ray = Ray::new(*collision.position(), sample.direction).with_time(self.time);
+            let transmittance = world.transmittance(&shadow_ray, sample.distance);
+            if transmittance <= 0.0 {
+                continue;
+            }
+            light += scattered.blend(&sample.intensity) * transmittance;
+        }
+        light
     }
+
+    /// Light coming straight from a direction chosen by the background, for the ones that can be sampled.
+    fn sample_background(&self, world: &World, collision: &Collision) -> Color {
+        let background = world.background();
+        let direction = match background.random_direction() {
+            Some(direction) => direction,
+            None => return Color::zeros(),
+        };
+        let light_pdf = background.pdf_value(&direction);
+        let scattered = self.scattered(collision, &direction);
+        if light_pdf <= 0.0 || scattered == Color::zeros() {
+            return Color::zeros();
+        }
+        let shadow_ray = Ray::new(*collision.position(), direction).with_time(self.time);
+        let transmittance = world.transmittance(&shadow_ray, f64::INFINITY);
+        if transmittance <= 0.0 {
+            return Color::zeros();
+        }
+        let bounce_pdf = collision.pdf(self, &direction);
+        scattered.blend(&background.color(&direction))
+            * transmittance
+            * power_heuristic(light_pdf, bounce_pdf)
+            / light_pdf
+    }
+
+    /// Part of the light coming from `direction` that the surface (or volume) sends back along the ray.
+    fn scattered(&self, collision: &Collision, direction: &Vector3<f64>) -> Color {
+        let cosine = if collision.is_volumetric() {
+            1.0
+        } else {
+            collision.normal().dot(direction).abs()
+        };
+        collision.eval(self, direction) * cosine
+    }
+
+    fn background_color(&self, world: &World) -> Color {
+        world.background().color(self.direction())
+    }
+}
+
+/// Multiple importance sampling weight of a sample drawn with `pdf` when `other_pdf` could also have produced it.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let pdf_squared = pdf * pdf;
+    pdf_squared / (pdf_squared + other_pdf * other_pdf)
 }
@@ -0,0 +1,296 @@
+use std::borrow::Borrow;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+const MAX_STEPS: usize = 512;
+// Distance to the surface under which the ray is considered on it, in fraction of the size of the bounds
+const RELATIVE_EPSILON: f64 = 2.5e-5;
+
+type DistanceFn = Box<dyn Fn(&Vector3<f64>) -> f64>;
+
+/// Signed distance to a surface, negative inside, built from primitives and operators.
+///
+/// Some operators like `twisted` only give an estimate of the distance, trace them with a smaller
+/// `SdfShape::with_step_scale`.
+pub struct Sdf {
+    distance: DistanceFn,
+}
+
+impl Sdf {
+    /// `distance` must never be larger than the actual distance to the surface.
+    pub fn new(distance: impl Fn(&Vector3<f64>) -> f64 + 'static) -> Sdf {
+        Sdf {
+            distance: Box::new(distance),
+        }
+    }
+
+    pub fn sphere(radius: f64) -> Sdf {
+        Sdf::new(move |p| p.magnitude() - radius)
+    }
+
+    /// Box centered on the origin, `half_extents` from the center to the faces.
+    pub fn cuboid(half_extents: Vector3<f64>) -> Sdf {
+        Sdf::new(move |p| {
+            let q = p.abs() - half_extents;
+            q.sup(&Vector3::zeros()).magnitude() + q.max().min(0.0)
+        })
+    }
+
+    /// Ring around the y axis, like `Torus`.
+    pub fn torus(major_radius: f64, minor_radius: f64) -> Sdf {
+        Sdf::new(move |p| {
+            Vector2::new(p.xz().magnitude() - major_radius, p.y).magnitude() - minor_radius
+        })
+    }
+
+    pub fn distance(&self, position: &Vector3<f64>) -> f64 {
+        (self.distance)(position)
+    }
+
+    pub fn union(self, other: Sdf) -> Sdf {
+        Sdf::new(move |p| self.distance(p).min(other.distance(p)))
+    }
+
+    pub fn intersection(self, other: Sdf) -> Sdf {
+        Sdf::new(move |p| self.distance(p).max(other.distance(p)))
+    }
+
+    /// Inside `self` but not `other`.
+    pub fn difference(self, other: Sdf) -> Sdf {
+        Sdf::new(move |p| self.distance(p).max(-other.distance(p)))
+    }
+
+    /// Union blending the shapes together where they are closer than `smoothness`.
+    pub fn smooth_union(self, other: Sdf, smoothness: f64) -> Sdf {
+        Sdf::new(move |p| {
+            let (a, b) = (self.distance(p), other.distance(p));
+            let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0.0, 1.0);
+            b * (1.0 - h) + a * h - smoothness * h * (1.0 - h)
+        })
+    }
+
+    pub fn translated(self, offset: Vector3<f64>) -> Sdf {
+        Sdf::new(move |p| self.distance(&(p - offset)))
+    }
+
+    /// Scales around the origin by the same factor along every axis.
+    pub fn scaled(self, factor: f64) -> Sdf {
+        Sdf::new(move |p| self.distance(&(p / factor)) * factor)
+    }
+
+    /// Copies of the shape every `period` along each axis, the shape must fit in a period around the origin.
+    pub fn repeated(self, period: Vector3<f64>) -> Sdf {
+        Sdf::new(move |p| {
+            let cell = p.zip_map(&period, |x, period| x - period * (x / period).round());
+            self.distance(&cell)
+        })
+    }
+
+    /// Rotates each slice of the shape around the y axis by `rate` radians per unit of height.
+    pub fn twisted(self, rate: f64) -> Sdf {
+        Sdf::new(move |p| {
+            let (sin, cos) = (rate * p.y).sin_cos();
+            self.distance(&Vector3::new(
+                cos * p.x - sin * p.z,
+                p.y,
+                sin * p.x + cos * p.z,
+            ))
+        })
+    }
+}
+
+/// Surface where a signed distance is 0, found by sphere tracing: the ray advances by the distance to
+/// the surface until it's on it.
+///
+/// Texture coordinates are the positions along the two axes most parallel to the surface,
+/// so textures repeat every unit.
+pub struct SdfShape {
+    sdf: Sdf,
+    bounds: AABB,
+    // distance to the surface under which the ray is considered on it
+    epsilon: f64,
+    step_scale: f64,
+    material: Box<dyn Material>,
+}
+
+impl SdfShape {
+    /// `bounds` must contain the whole surface, nothing is traced outside of it.
+    /// The precision of the tracing follows their size.
+    pub fn new(sdf: Sdf, bounds: AABB, material: Box<dyn Material>) -> SdfShape {
+        SdfShape {
+            sdf,
+            epsilon: bounds.extent().max() * RELATIVE_EPSILON,
+            bounds,
+            step_scale: 1.0,
+            material,
+        }
+    }
+
+    /// Fraction of the distance the ray advances at each step, below 1 for distances that are overestimated.
+    pub fn with_step_scale(mut self, step_scale: f64) -> SdfShape {
+        self.step_scale = step_scale;
+        self
+    }
+
+    /// Axis the normal at `position` is the most aligned with.
+    fn main_axis(&self, position: &Vector3<f64>) -> usize {
+        self.normal_at_position(position).abs().imax()
+    }
+}
+
+impl Shape for SdfShape {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let (start, end) = self.bounds.intersection(ray, t_min, t_max)?;
+        let length = ray.direction().magnitude();
+        let mut t = start;
+        // A ray leaving the surface, like a bounce, must get away from it before hitting it again,
+        // otherwise it would hit where it starts. Rays coming from outside of the bounds can't be leaving it,
+        // and the surface can touch the bounds where they enter.
+        let mut left_surface = start > t_min;
+        for _ in 0..MAX_STEPS {
+            // Works from inside the shape too, for rays refracted in it.
+            let distance = self.sdf.distance(&ray.at(t)).abs();
+            if distance >= self.epsilon {
+                left_surface = true;
+            } else if left_surface && t > t_min {
+                return Some(Collision::new(t, ray.at(t), self));
+            }
+            t += (distance * self.step_scale).max(self.epsilon * 0.5) / length;
+            if t >= end {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Gradient of the distance, from central differences.
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let gradient = Vector3::from_fn(|axis, _| {
+            let mut offset = Vector3::zeros();
+            offset[axis] = self.epsilon;
+            self.sdf.distance(&(position + offset)) - self.sdf.distance(&(position - offset))
+        });
+        gradient
+            .try_normalize(1e-12)
+            .unwrap_or_else(|| Vector3::new(0.0, 1.0, 0.0))
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let axis = self.main_axis(position);
+        Vector2::new(position[(axis + 1) % 3], position[(axis + 2) % 3])
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let mut tangent = Vector3::zeros();
+        tangent[(self.main_axis(position) + 1) % 3] = 1.0;
+        tangent
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    const EPSILON: f64 = 1e-4;
+
+    fn trace(
+        sdf: Sdf,
+        half_bounds: f64,
+        origin: Vector3<f64>,
+        direction: Vector3<f64>,
+    ) -> Option<f64> {
+        let bounds = AABB::new(Vector3::repeat(-half_bounds), Vector3::repeat(half_bounds));
+        let shape = SdfShape::new(sdf, bounds, Box::new(Lambertian::new(0.5)));
+        let collision = shape.collide(
+            &Ray::new(origin, direction),
+            0.001 * half_bounds,
+            f64::INFINITY,
+        )?;
+        Some(collision.dist_from_origin())
+    }
+
+    fn hit(origin: Vector3<f64>, direction: Vector3<f64>) -> Option<f64> {
+        trace(Sdf::sphere(1.0), 2.0, origin, direction)
+    }
+
+    #[test]
+    fn hits_the_surface() {
+        let t = hit(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((t - 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn rays_leaving_the_surface_do_not_hit_it_again() {
+        let on_surface = Vector3::new(0.0, 0.0, 1.0);
+        assert!(hit(on_surface, Vector3::new(0.0, 0.0, 1.0)).is_none());
+        assert!(hit(on_surface, Vector3::new(0.0, 1.0, 1.0)).is_none());
+        // Refracted inside, the ray goes through to the other side.
+        let t = hit(on_surface, Vector3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((t - 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hits_surfaces_touching_the_bounds() {
+        let cube = || Sdf::cuboid(Vector3::repeat(1.0));
+        let t = trace(
+            cube(),
+            1.0,
+            Vector3::new(0.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        );
+        assert!((t.unwrap() - 4.0).abs() < EPSILON);
+        let t = trace(
+            Sdf::sphere(1.0),
+            1.0,
+            Vector3::new(0.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        );
+        assert!((t.unwrap() - 4.0).abs() < EPSILON);
+        // Bouncing off the face the bounds touch.
+        let on_face = Vector3::new(0.5, 0.5, 1.0);
+        assert!(trace(cube(), 1.0, on_face, Vector3::new(0.0, 1.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn precision_follows_the_size_of_the_shape() {
+        for &scale in [1e-3, 1e3].iter() {
+            let sphere = || Sdf::sphere(1.0).scaled(scale);
+            let origin = Vector3::new(0.0, 0.0, 5.0 * scale);
+            let t = trace(sphere(), 2.0 * scale, origin, Vector3::new(0.0, 0.0, -1.0)).unwrap();
+            assert!((t / scale - 4.0).abs() < EPSILON, "{} {}", scale, t);
+            // Leaving the surface, and going through it.
+            let on_surface = Vector3::new(0.0, 0.0, scale);
+            assert!(trace(
+                sphere(),
+                2.0 * scale,
+                on_surface,
+                Vector3::new(0.0, 1.0, 1.0)
+            )
+            .is_none());
+            let t = trace(
+                sphere(),
+                2.0 * scale,
+                on_surface,
+                Vector3::new(0.0, 0.0, -1.0),
+            )
+            .unwrap();
+            assert!((t / scale - 2.0).abs() < EPSILON, "{} {}", scale, t);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use nalgebra::{Vector2, Vector3};
 
 use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
 use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
 use crate::shapes::ray::Ray;
 
 pub trait Shape {
@@ -11,5 +13,49 @@ pub trait Shape {
 
     fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64>;
 
+    /// Unit vector tangent to the surface along which the first texture coordinate grows,
+    /// it orients normal maps.
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        orthonormal_basis(&self.normal_at_position(position)).0
+    }
+
+    /// Every collision of the whole line of the ray with the shape, sorted by distance.
+    /// For shapes enclosing a volume, `Collision::is_entering` tells where the ray is inside.
+    fn all_collisions(&self, ray: &Ray) -> Vec<Collision<'_>> {
+        let mut collisions = Vec::new();
+        let mut t_min = f64::NEG_INFINITY;
+        // `collide` only returns collisions strictly after `t_min`.
+        while let Some(collision) = self.collide(ray, t_min, f64::INFINITY) {
+            t_min = collision.dist_from_origin();
+            collisions.push(collision);
+        }
+        collisions
+    }
+
     fn material(&self) -> &dyn Material;
+
+    /// Whether some of the surface emits light, the shape is then sampled directly as a light.
+    fn is_emissive(&self) -> bool {
+        self.material().is_emissive()
+    }
+
+    /// Box containing the whole shape, `None` if the shape is unbounded.
+    fn bounding_box(&self) -> Option<AABB>;
+
+    /// Fraction of the light going through the shape between `t_min` and `t_max` along the ray,
+    /// for volumes. `None` for opaque shapes, which block the light when `collide` finds a hit.
+    fn transmittance(&self, _ray: &Ray, _t_min: f64, _t_max: f64) -> Option<f64> {
+        None
+    }
+
+    /// Probability density (over solid angle) of `random_direction` returning `direction` from `origin`.
+    fn pdf_value(&self, _origin: &Vector3<f64>, _direction: &Vector3<f64>) -> f64 {
+        0.0
+    }
+
+    /// Random direction from `origin` toward the shape, used to sample lights directly.
+    /// `None` if the shape doesn't support it.
+    fn random_direction(&self, _origin: &Vector3<f64>) -> Option<Vector3<f64>> {
+        None
+    }
 }
@@ -1,18 +1,25 @@
 use std::borrow::Borrow;
+use std::cell::RefCell;
 
 use nalgebra::{Vector2, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 
 use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
 use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
 use crate::shapes::ray::Ray;
+use crate::world::T_MIN;
 
 use super::shape::Shape;
-use std::f64::consts::PI;
+use std::f64::consts::{PI, TAU};
 
 pub struct Sphere {
     center: Vector3<f64>,
     radius: f64,
     material: Box<dyn Material>,
+    rng: RefCell<SmallRng>,
 }
 
 impl Sphere {
@@ -21,10 +28,23 @@ impl Sphere {
             center,
             radius,
             material,
+            rng: RefCell::new(SmallRng::from_entropy()),
         }
     }
 }
 
+impl Sphere {
+    /// Cosine of the half angle of the cone seeing the sphere from `origin`, `None` from inside the sphere.
+    fn cos_theta_max(&self, origin: &Vector3<f64>) -> Option<f64> {
+        let distance_squared = (self.center - origin).magnitude_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / distance_squared).sqrt())
+    }
+}
+
 impl Shape for Sphere {
     fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
         let oc = ray.origin() - self.center;
@@ -72,7 +92,46 @@ impl Shape for Sphere {
         )
     }
 
+    /// Derivative of the position along the longitude, the first texture coordinate.
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let normal = position - self.center;
+        Vector3::new(-normal.z, 0.0, normal.x)
+            .try_normalize(1e-12)
+            // Poles
+            .unwrap_or_else(|| orthonormal_basis(&self.normal_at_position(position)).0)
+    }
+
     fn material(&self) -> &dyn Material {
         self.material.borrow()
     }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        let radius = Vector3::repeat(self.radius.abs());
+        Some(AABB::new(self.center - radius, self.center + radius))
+    }
+
+    fn pdf_value(&self, origin: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        if self
+            .collide(&Ray::new(*origin, *direction), T_MIN, f64::INFINITY)
+            .is_none()
+        {
+            return 0.0;
+        }
+        match self.cos_theta_max(origin) {
+            Some(cos_theta_max) => 1.0 / (TAU * (1.0 - cos_theta_max)),
+            None => 0.0,
+        }
+    }
+
+    fn random_direction(&self, origin: &Vector3<f64>) -> Option<Vector3<f64>> {
+        // Uniform direction in the cone of directions seeing the sphere.
+        let cos_theta_max = self.cos_theta_max(origin)?;
+        let mut rng = self.rng.borrow_mut();
+        let z = 1.0 + rng.gen_range(0.0, 1.0) * (cos_theta_max - 1.0);
+        let phi = TAU * rng.gen_range(0.0, 1.0);
+        let sin_theta = (1.0 - z * z).sqrt();
+        let axis = (self.center - origin).normalize();
+        let (tangent, bitangent) = orthonormal_basis(&axis);
+        Some(tangent * (phi.cos() * sin_theta) + bitangent * (phi.sin() * sin_theta) + axis * z)
+    }
 }
@@ -0,0 +1,273 @@
+use std::borrow::Borrow;
+use std::f64::consts::{PI, TAU};
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Ring around the y axis, a tube of radius `minor_radius` whose center is at `major_radius` from `center`.
+///
+/// The first texture coordinate goes around the y axis like on a `Sphere`, the second one around the tube.
+pub struct Torus {
+    center: Vector3<f64>,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Box<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vector3<f64>,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Box<dyn Material>,
+    ) -> Torus {
+        Torus {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    /// Point of the circle at the center of the tube closest to `local`, relative to `center`.
+    fn closest_on_circle(&self, local: &Vector3<f64>) -> Vector3<f64> {
+        let radial = Vector3::new(local.x, 0.0, local.z)
+            .try_normalize(1e-12)
+            .unwrap_or_else(|| Vector3::new(1.0, 0.0, 0.0));
+        radial * self.major_radius
+    }
+}
+
+impl Torus {
+    /// Distances along the ray of every crossing of its whole line with the torus, sorted.
+    fn roots(&self, ray: &Ray) -> Vec<f64> {
+        // The quartic is solved from where the line enters the bounding box, with a unit direction,
+        // so its coefficients stay small.
+        let start = match self
+            .bounding_box()
+            .and_then(|bounds| bounds.intersection(ray, f64::NEG_INFINITY, f64::INFINITY))
+        {
+            Some((start, _)) => start,
+            None => return Vec::new(),
+        };
+        let length = ray.direction().magnitude();
+        let direction = ray.direction() / length;
+        let origin = ray.at(start) - self.center;
+
+        // (|p|² + R² - r²)² = 4R²(x² + z²) with p = origin + s * direction
+        let major_squared = self.major_radius * self.major_radius;
+        let alpha = origin.dot(&direction);
+        let beta = origin.magnitude_squared() + major_squared - self.minor_radius.powi(2);
+        let coefficients = [
+            4.0 * alpha,
+            4.0 * alpha * alpha + 2.0 * beta
+                - 4.0 * major_squared * direction.xz().magnitude_squared(),
+            4.0 * alpha * beta - 8.0 * major_squared * origin.xz().dot(&direction.xz()),
+            beta * beta - 4.0 * major_squared * origin.xz().magnitude_squared(),
+        ];
+        let mut roots: Vec<f64> = solve_quartic(coefficients)
+            .into_iter()
+            .map(|s| start + s / length)
+            .filter(|t| t.is_finite())
+            .collect();
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+}
+
+impl Shape for Torus {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let hit_distance_from_ray_origin = self
+            .roots(ray)
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)?;
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            ray.at(hit_distance_from_ray_origin),
+            self,
+        ))
+    }
+
+    /// Solving the quartic again from a previous hit could find it again, all the roots come from one solve.
+    fn all_collisions(&self, ray: &Ray) -> Vec<Collision<'_>> {
+        self.roots(ray)
+            .into_iter()
+            .map(|t| Collision::new(t, ray.at(t), self))
+            .collect()
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let local = position - self.center;
+        (local - self.closest_on_circle(&local))
+            .try_normalize(1e-12)
+            .unwrap_or_else(|| Vector3::new(0.0, 1.0, 0.0))
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let local = position - self.center;
+        let around_tube = local.y.atan2(local.xz().magnitude() - self.major_radius);
+        Vector2::new(
+            (1.0 + local.z.atan2(local.x) / PI) * 0.5,
+            (1.0 + around_tube / PI) * 0.5,
+        )
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let local = position - self.center;
+        Vector3::new(-local.z, 0.0, local.x)
+            .try_normalize(1e-12)
+            .unwrap_or_else(|| orthonormal_basis(&self.normal_at_position(position)).0)
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        let outer = self.major_radius.abs() + self.minor_radius.abs();
+        let extent = Vector3::new(outer, self.minor_radius.abs(), outer);
+        Some(AABB::new(self.center - extent, self.center + extent))
+    }
+}
+
+/// Real roots of x⁴ + a x³ + b x² + c x + d with `[a, b, c, d]`, with Ferrari's method.
+fn solve_quartic(coefficients: [f64; 4]) -> Vec<f64> {
+    let [a, b, c, d] = coefficients;
+    // Depressed quartic y⁴ + p y² + q y + r with x = y - a / 4
+    let shift = -a / 4.0;
+    let a_squared = a * a;
+    let p = b - 3.0 * a_squared / 8.0;
+    let q = c - a * b / 2.0 + a_squared * a / 8.0;
+    let r = d - a * c / 4.0 + a_squared * b / 16.0 - 3.0 * a_squared * a_squared / 256.0;
+
+    let mut roots = Vec::with_capacity(4);
+    if q.abs() < 1e-12 {
+        // Quadratic in y².
+        for y_squared in solve_quadratic(p, r) {
+            if y_squared >= 0.0 {
+                roots.push(y_squared.sqrt());
+                roots.push(-y_squared.sqrt());
+            }
+        }
+    } else {
+        // Any root m > 0 of the resolvent cubic splits the quartic in two quadratics.
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        if m <= 0.0 {
+            return Vec::new();
+        }
+        let s = (2.0 * m).sqrt();
+        roots.extend(solve_quadratic(-s, p / 2.0 + m + q / (2.0 * s)));
+        roots.extend(solve_quadratic(s, p / 2.0 + m - q / (2.0 * s)));
+    }
+
+    let polynomial = |x: f64| (((x + a) * x + b) * x + c) * x + d;
+    let derivative = |x: f64| ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+    roots
+        .into_iter()
+        .map(|y| {
+            // A few Newton steps make up for the precision lost in the resolvent.
+            let mut x = y + shift;
+            for _ in 0..2 {
+                let slope = derivative(x);
+                if slope.abs() > 1e-12 {
+                    x -= polynomial(x) / slope;
+                }
+            }
+            x
+        })
+        .collect()
+}
+
+/// Real roots of x² + b x + c.
+fn solve_quadratic(b: f64, c: f64) -> Vec<f64> {
+    let discriminant = b * b - 4.0 * c;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    let root = discriminant.sqrt();
+    vec![(-b - root) / 2.0, (-b + root) / 2.0]
+}
+
+/// Largest real root of x³ + a x² + b x + c.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    if r * r < q * q * q {
+        // Three real roots.
+        let theta = (r / (q * q * q).sqrt()).acos();
+        let scale = -2.0 * q.sqrt();
+        [0.0, TAU, -TAU]
+            .iter()
+            .map(|offset| scale * ((theta + offset) / 3.0).cos() - a / 3.0)
+            .fold(f64::NEG_INFINITY, f64::max)
+    } else {
+        let big = -r.signum() * (r.abs() + (r * r - q * q * q).sqrt()).cbrt();
+        let small = if big == 0.0 { 0.0 } else { q / big };
+        big + small - a / 3.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    fn hit(origin: Vector3<f64>, direction: Vector3<f64>) -> Option<f64> {
+        let torus = Torus::new(Vector3::zeros(), 1.0, 0.25, Box::new(Lambertian::new(0.5)));
+        let collision = torus.collide(&Ray::new(origin, direction), 0.0, 100.0)?;
+        Some(collision.dist_from_origin())
+    }
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let mut roots = solve_quartic([-10.0, 35.0, -50.0, 24.0]);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0].iter()) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        // (x² + 1)(x² + 2) has no real root
+        assert!(solve_quartic([0.0, 3.0, 0.0, 2.0]).is_empty());
+    }
+
+    #[test]
+    fn hits_outer_and_inner_sides() {
+        let t = hit(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((t - 3.75).abs() < 1e-9);
+        // From the center of the hole, the inner side of the tube.
+        let t = hit(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((t - 0.75).abs() < 1e-9);
+        let t = hit(Vector3::new(1.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((t - 4.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn all_collisions_are_found_once() {
+        let torus = Torus::new(Vector3::zeros(), 1.0, 0.25, Box::new(Lambertian::new(0.5)));
+        let ray = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let collisions = torus.all_collisions(&ray);
+        assert_eq!(collisions.len(), 4);
+        for (i, (collision, expected)) in collisions
+            .iter()
+            .zip([3.75, 4.25, 5.75, 6.25].iter())
+            .enumerate()
+        {
+            assert!((collision.dist_from_origin() - expected).abs() < 1e-9);
+            assert_eq!(collision.is_entering(&ray), i % 2 == 0);
+        }
+    }
+
+    #[test]
+    fn misses_through_the_hole() {
+        assert!(hit(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0)).is_none());
+        assert!(hit(Vector3::new(0.0, 0.5, 5.0), Vector3::new(0.0, 0.0, -1.0)).is_none());
+    }
+}
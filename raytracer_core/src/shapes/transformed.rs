@@ -0,0 +1,314 @@
+use std::rc::Rc;
+
+use nalgebra::{Matrix3, Matrix4, Point3, Unit, UnitQuaternion, Vector2, Vector3, U3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Affine transform from the space of a shape to the world, with its inverse.
+#[derive(Debug, Clone)]
+pub struct Transform {
+    to_world: Matrix4<f64>,
+    to_object: Matrix4<f64>,
+}
+
+impl Transform {
+    /// `None` if `matrix` can't be inverted.
+    pub fn new(matrix: Matrix4<f64>) -> Option<Transform> {
+        Some(Transform {
+            to_world: matrix,
+            to_object: matrix.try_inverse()?,
+        })
+    }
+
+    /// Scaling, then rotation, then translation. The inverse is built from the parts instead of
+    /// inverting the matrix, `scale` must not have a 0 component.
+    pub(crate) fn from_parts(
+        translation: &Vector3<f64>,
+        rotation: &UnitQuaternion<f64>,
+        scale: &Vector3<f64>,
+    ) -> Transform {
+        Transform {
+            to_world: Matrix4::new_translation(translation)
+                * rotation.to_homogeneous()
+                * Matrix4::new_nonuniform_scaling(scale),
+            to_object: Matrix4::new_nonuniform_scaling(&scale.map(|factor| 1.0 / factor))
+                * rotation.inverse().to_homogeneous()
+                * Matrix4::new_translation(&-translation),
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4<f64> {
+        &self.to_world
+    }
+
+    /// Transform applying `self` then `outer`.
+    pub fn then(&self, outer: &Transform) -> Transform {
+        Transform {
+            to_world: outer.to_world * self.to_world,
+            to_object: self.to_object * outer.to_object,
+        }
+    }
+
+    pub fn point_to_world(&self, point: &Vector3<f64>) -> Vector3<f64> {
+        self.to_world.transform_point(&Point3::from(*point)).coords
+    }
+
+    pub fn point_to_object(&self, point: &Vector3<f64>) -> Vector3<f64> {
+        self.to_object.transform_point(&Point3::from(*point)).coords
+    }
+
+    pub fn vector_to_world(&self, vector: &Vector3<f64>) -> Vector3<f64> {
+        self.to_world.transform_vector(vector)
+    }
+
+    pub fn vector_to_object(&self, vector: &Vector3<f64>) -> Vector3<f64> {
+        self.to_object.transform_vector(vector)
+    }
+
+    /// Normals are transformed by the inverse transpose so they stay orthogonal to the surface.
+    pub fn normal_to_world(&self, normal: &Vector3<f64>) -> Vector3<f64> {
+        let linear: Matrix3<f64> = self.to_object.fixed_slice::<U3, U3>(0, 0).transpose();
+        (linear * normal).normalize()
+    }
+
+    fn determinant(&self) -> f64 {
+        self.to_world.fixed_slice::<U3, U3>(0, 0).determinant()
+    }
+}
+
+/// Shape moved, rotated or scaled by an affine transform.
+///
+/// The shape is shared behind an `Rc`, so the same geometry, like a big mesh, can be placed many times
+/// while being stored once. Volumes keep their density per unit of length of the shape space.
+pub struct Transformed {
+    shape: Rc<dyn Shape>,
+    transform: Rc<Transform>,
+    bounds: Option<AABB>,
+}
+
+impl Transformed {
+    /// Panics if `matrix` can't be inverted.
+    pub fn new(shape: Rc<dyn Shape>, matrix: Matrix4<f64>) -> Transformed {
+        let transform = Transform::new(matrix).expect("transform matrix can't be inverted");
+        let bounds = shape
+            .bounding_box()
+            .map(|bounds| transformed_bounds(&bounds, &transform));
+        Transformed {
+            shape,
+            transform: Rc::new(transform),
+            bounds,
+        }
+    }
+
+    /// Shape as it is, to be placed with the `translated`, `rotated` and `scaled` methods.
+    pub fn identity(shape: Rc<dyn Shape>) -> Transformed {
+        Transformed::new(shape, Matrix4::identity())
+    }
+
+    /// Applies `matrix` after the current transform.
+    pub fn then(self, matrix: Matrix4<f64>) -> Transformed {
+        Transformed::new(self.shape, matrix * self.transform.to_world)
+    }
+
+    pub fn translated(self, offset: Vector3<f64>) -> Transformed {
+        self.then(Matrix4::new_translation(&offset))
+    }
+
+    /// Rotation of `angle` degrees around `axis`, which goes through the origin.
+    pub fn rotated(self, axis: Vector3<f64>, angle: f64) -> Transformed {
+        self.then(Matrix4::from_axis_angle(
+            &Unit::new_normalize(axis),
+            angle.to_radians(),
+        ))
+    }
+
+    /// Scales around the origin, by a different factor along each axis.
+    pub fn scaled(self, factors: Vector3<f64>) -> Transformed {
+        self.then(Matrix4::new_nonuniform_scaling(&factors))
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn ray_to_object(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.transform.point_to_object(ray.origin()),
+            self.transform.vector_to_object(ray.direction()),
+        )
+        .with_spread(ray.spread())
+        .with_time(ray.time())
+    }
+}
+
+impl Shape for Transformed {
+    /// The distance along the ray stays the same since the direction of the ray is transformed too.
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let collision = self.shape.collide(&self.ray_to_object(ray), t_min, t_max)?;
+        Some(collision.transformed(&self.transform))
+    }
+
+    fn all_collisions(&self, ray: &Ray) -> Vec<Collision<'_>> {
+        self.shape
+            .all_collisions(&self.ray_to_object(ray))
+            .into_iter()
+            .map(|collision| collision.transformed(&self.transform))
+            .collect()
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let normal = self
+            .shape
+            .normal_at_position(&self.transform.point_to_object(position));
+        self.transform.normal_to_world(&normal)
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        self.shape
+            .texture_coords_at_position(&self.transform.point_to_object(position))
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let tangent = self
+            .shape
+            .tangent_at_position(&self.transform.point_to_object(position));
+        self.transform.vector_to_world(&tangent).normalize()
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.shape.material()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.shape.is_emissive()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        self.bounds
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        self.shape
+            .transmittance(&self.ray_to_object(ray), t_min, t_max)
+    }
+
+    /// Density of the shape space direction, scaled by how much the transform stretches solid angles.
+    fn pdf_value(&self, origin: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        let object_direction = self.transform.vector_to_object(direction).normalize();
+        let pdf = self
+            .shape
+            .pdf_value(&self.transform.point_to_object(origin), &object_direction);
+        let stretch = self
+            .transform
+            .vector_to_world(&object_direction)
+            .magnitude();
+        pdf * stretch.powi(3) / self.transform.determinant().abs()
+    }
+
+    fn random_direction(&self, origin: &Vector3<f64>) -> Option<Vector3<f64>> {
+        let direction = self
+            .shape
+            .random_direction(&self.transform.point_to_object(origin))?;
+        Some(self.transform.vector_to_world(&direction))
+    }
+}
+
+/// Box containing the 8 transformed corners of `bounds`.
+pub(crate) fn transformed_bounds(bounds: &AABB, transform: &Transform) -> AABB {
+    let (min, max) = (bounds.min(), bounds.max());
+    (0..8)
+        .map(|corner| {
+            Vector3::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            )
+        })
+        .map(|corner| transform.point_to_world(&corner))
+        .fold(AABB::empty(), |bounds, corner| {
+            bounds.surrounding(&AABB::new(corner, corner))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::quad::Quad;
+    use crate::shapes::sphere::Sphere;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::f64::consts::{PI, TAU};
+
+    /// Stretched unevenly and turned, so solid angles change differently in every direction.
+    fn place(shape: Rc<dyn Shape>) -> Transformed {
+        Transformed::identity(shape)
+            .scaled(Vector3::new(2.0, 0.5, 1.0))
+            .rotated(Vector3::new(1.0, 1.0, 0.0), 30.0)
+            .translated(Vector3::new(0.5, 0.0, 3.0))
+    }
+
+    #[test]
+    fn pdf_matches_the_shape_placed_in_the_world() {
+        let transformed = place(Rc::new(Quad::new_xy(
+            (0.0, 1.0),
+            (0.0, 1.0),
+            0.0,
+            Box::new(Lambertian::new(0.5)),
+        )));
+        let transform = transformed.transform();
+        let quad = Quad::new(
+            transform.point_to_world(&Vector3::zeros()),
+            transform.vector_to_world(&Vector3::new(1.0, 0.0, 0.0)),
+            transform.vector_to_world(&Vector3::new(0.0, 1.0, 0.0)),
+            Box::new(Lambertian::new(0.5)),
+        );
+        let origin = Vector3::new(-1.0, 2.0, -1.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let point = Vector3::new(rng.gen_range(0.0, 1.0), rng.gen_range(0.0, 1.0), 0.0);
+            let direction = (transform.point_to_world(&point) - origin).normalize();
+            let expected = quad.pdf_value(&origin, &direction);
+            let pdf = transformed.pdf_value(&origin, &direction);
+            assert!(
+                (pdf - expected).abs() < 1e-9 * expected,
+                "{} {}",
+                pdf,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let ellipsoid = place(Rc::new(Sphere::new(
+            Vector3::zeros(),
+            1.0,
+            Box::new(Lambertian::new(0.5)),
+        )));
+        let origin = Vector3::new(0.0, 0.5, 0.0);
+        // Midpoint rule over the sphere of directions, in spherical coordinates around +z.
+        let (steps_theta, steps_phi) = (300, 600);
+        let (step_theta, step_phi) = (PI / steps_theta as f64, TAU / steps_phi as f64);
+        let mut integral = 0.0;
+        for i in 0..steps_theta {
+            let theta = (i as f64 + 0.5) * step_theta;
+            for j in 0..steps_phi {
+                let phi = (j as f64 + 0.5) * step_phi;
+                let direction = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                integral +=
+                    ellipsoid.pdf_value(&origin, &direction) * theta.sin() * step_theta * step_phi;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01, "{}", integral);
+    }
+}
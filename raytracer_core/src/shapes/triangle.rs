@@ -0,0 +1,229 @@
+use std::borrow::Borrow;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::Ray;
+
+use super::shape::Shape;
+
+/// Texture coordinates of the vertices when none are given, the barycentric weights of `b` and `c`.
+pub(crate) fn default_texture_coords() -> [Vector2<f64>; 3] {
+    [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(0.0, 1.0),
+    ]
+}
+
+/// Triangle with optional per-vertex normals (smooth shading) and texture coordinates.
+/// The geometric normal is `(b - a) × (c - a)`.
+pub struct Triangle {
+    vertices: [Vector3<f64>; 3],
+    normals: Option<[Vector3<f64>; 3]>,
+    texture_coords: Option<[Vector2<f64>; 3]>,
+    material: Box<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(
+        a: Vector3<f64>,
+        b: Vector3<f64>,
+        c: Vector3<f64>,
+        material: Box<dyn Material>,
+    ) -> Triangle {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            texture_coords: None,
+            material,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: [Vector3<f64>; 3]) -> Triangle {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_texture_coords(mut self, texture_coords: [Vector2<f64>; 3]) -> Triangle {
+        self.texture_coords = Some(texture_coords);
+        self
+    }
+}
+
+impl Shape for Triangle {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let [a, b, c] = &self.vertices;
+        let hit_distance_from_ray_origin = intersect(ray, a, b, c, t_min, t_max)?;
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            ray.at(hit_distance_from_ray_origin),
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let [a, b, c] = &self.vertices;
+        match &self.normals {
+            Some(normals) => interpolate_normal(&barycentric(position, a, b, c), normals),
+            None => (b - a).cross(&(c - a)).normalize(),
+        }
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let [a, b, c] = &self.vertices;
+        let weights = barycentric(position, a, b, c);
+        match &self.texture_coords {
+            Some(coords) => interpolate_texture_coords(&weights, coords),
+            None => Vector2::new(weights.y, weights.z),
+        }
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let texture_coords = self.texture_coords.unwrap_or_else(default_texture_coords);
+        tangent(&self.vertices, &texture_coords)
+            .unwrap_or_else(|| orthonormal_basis(&self.normal_at_position(position)).0)
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.material.borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        Some(bounding_box(&self.vertices))
+    }
+}
+
+/// Möller–Trumbore intersection, returns the distance of the hit along the ray.
+pub(crate) fn intersect(
+    ray: &Ray,
+    a: &Vector3<f64>,
+    b: &Vector3<f64>,
+    c: &Vector3<f64>,
+    t_min: f64,
+    t_max: f64,
+) -> Option<f64> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction().cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse_determinant = 1.0 / determinant;
+    let s = ray.origin() - a;
+    let u = s.dot(&p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let v = ray.direction().dot(&q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(&q) * inverse_determinant;
+    if t < t_max && t > t_min {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Weights of each vertex for a point lying in the triangle.
+pub(crate) fn barycentric(
+    position: &Vector3<f64>,
+    a: &Vector3<f64>,
+    b: &Vector3<f64>,
+    c: &Vector3<f64>,
+) -> Vector3<f64> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = position - a;
+    let d00 = ab.dot(&ab);
+    let d01 = ab.dot(&ac);
+    let d11 = ac.dot(&ac);
+    let d20 = ap.dot(&ab);
+    let d21 = ap.dot(&ac);
+    let denominator = d00 * d11 - d01 * d01;
+    let v = (d11 * d20 - d01 * d21) / denominator;
+    let w = (d00 * d21 - d01 * d20) / denominator;
+    Vector3::new(1.0 - v - w, v, w)
+}
+
+pub(crate) fn interpolate_normal(
+    weights: &Vector3<f64>,
+    normals: &[Vector3<f64>; 3],
+) -> Vector3<f64> {
+    (normals[0] * weights.x + normals[1] * weights.y + normals[2] * weights.z).normalize()
+}
+
+pub(crate) fn interpolate_texture_coords(
+    weights: &Vector3<f64>,
+    texture_coords: &[Vector2<f64>; 3],
+) -> Vector2<f64> {
+    texture_coords[0] * weights.x + texture_coords[1] * weights.y + texture_coords[2] * weights.z
+}
+
+/// Direction along which the first texture coordinate grows, `None` if the coordinates are degenerate.
+pub(crate) fn tangent(
+    vertices: &[Vector3<f64>; 3],
+    texture_coords: &[Vector2<f64>; 3],
+) -> Option<Vector3<f64>> {
+    let [a, b, c] = vertices;
+    let [ta, tb, tc] = texture_coords;
+    let (edge1, edge2) = (b - a, c - a);
+    let (delta1, delta2) = (tb - ta, tc - ta);
+    let determinant = delta1.x * delta2.y - delta2.x * delta1.y;
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    ((edge1 * delta2.y - edge2 * delta1.y) / determinant).try_normalize(1e-12)
+}
+
+pub(crate) fn bounding_box(vertices: &[Vector3<f64>; 3]) -> AABB {
+    vertices
+        .iter()
+        .fold(AABB::empty(), |bounds, vertex| {
+            bounds.surrounding(&AABB::new(*vertex, *vertex))
+        })
+        // Axis aligned triangles would give flat boxes.
+        .expand(1e-6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(origin: [f64; 3], direction: [f64; 3]) -> Option<f64> {
+        let ray = Ray::new(Vector3::from(origin), Vector3::from(direction));
+        let (a, b, c) = (
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        intersect(&ray, &a, &b, &c, 0.001, 100.0)
+    }
+
+    #[test]
+    fn hits_both_faces() {
+        assert_eq!(hit([0.25, 0.25, 2.0], [0.0, 0.0, -1.0]), Some(2.0));
+        assert_eq!(hit([0.25, 0.25, -2.0], [0.0, 0.0, 2.0]), Some(1.0));
+    }
+
+    #[test]
+    fn misses_outside_each_edge() {
+        assert_eq!(hit([-0.1, 0.5, 2.0], [0.0, 0.0, -1.0]), None);
+        assert_eq!(hit([0.5, -0.1, 2.0], [0.0, 0.0, -1.0]), None);
+        assert_eq!(hit([0.6, 0.6, 2.0], [0.0, 0.0, -1.0]), None);
+    }
+
+    #[test]
+    fn misses_parallel_rays_and_hits_out_of_the_interval() {
+        assert_eq!(hit([0.25, 0.25, 0.0], [1.0, 0.0, 0.0]), None);
+        assert_eq!(hit([0.25, 0.25, 2.0], [0.0, 0.0, 1.0]), None);
+        assert_eq!(hit([0.25, 0.25, 200.0], [0.0, 0.0, -1.0]), None);
+    }
+}
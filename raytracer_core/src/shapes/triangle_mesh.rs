@@ -0,0 +1,449 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use nalgebra::{Vector2, Vector3};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::materials::material::Material;
+use crate::shapes::aabb::AABB;
+use crate::shapes::bvh::Bvh;
+use crate::shapes::collision::Collision;
+use crate::shapes::plane::orthonormal_basis;
+use crate::shapes::ray::Ray;
+use crate::shapes::triangle;
+use crate::world::T_MIN;
+
+use super::shape::Shape;
+
+// Distance from the surface under which a position is looked up in a triangle
+const POSITION_MARGIN: f64 = 1e-6;
+
+#[derive(Debug)]
+pub enum MeshError {
+    Empty,
+    /// A face indexes past the end of one of the buffers, holds the index of the face.
+    InvalidFace(usize),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::Empty => write!(f, "mesh without triangles"),
+            MeshError::InvalidFace(face) => {
+                write!(f, "face {} indexes past the end of a mesh buffer", face)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Vertex attributes and materials shared by every triangle of a mesh.
+pub struct MeshData {
+    positions: Vec<Vector3<f64>>,
+    normals: Vec<Vector3<f64>>,
+    texture_coords: Vec<Vector2<f64>>,
+    materials: Vec<Rc<dyn Material>>,
+}
+
+/// Indices of a triangle in the mesh buffers.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub texture_coords: Option<[usize; 3]>,
+    pub material: usize,
+}
+
+pub struct MeshTriangle {
+    mesh: Rc<MeshData>,
+    face: MeshFace,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [&Vector3<f64>; 3] {
+        let [a, b, c] = self.face.positions;
+        [
+            &self.mesh.positions[a],
+            &self.mesh.positions[b],
+            &self.mesh.positions[c],
+        ]
+    }
+
+    pub fn face(&self) -> &MeshFace {
+        &self.face
+    }
+
+    fn area(&self) -> f64 {
+        let [a, b, c] = self.vertices();
+        (b - a).cross(&(c - a)).magnitude() / 2.0
+    }
+
+    /// Distance from `position` to its projection on the plane of the triangle,
+    /// plus how far the projection is outside of the triangle.
+    fn distance_to(&self, position: &Vector3<f64>) -> f64 {
+        let [a, b, c] = self.vertices();
+        let weights = triangle::barycentric(position, a, b, c);
+        let projected = a * weights.x + b * weights.y + c * weights.z;
+        let outside = weights.iter().map(|w| (-w).max(0.0)).sum::<f64>();
+        (projected - position).magnitude() + outside
+    }
+}
+
+impl Shape for MeshTriangle {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let [a, b, c] = self.vertices();
+        let hit_distance_from_ray_origin = triangle::intersect(ray, a, b, c, t_min, t_max)?;
+        Some(Collision::new(
+            hit_distance_from_ray_origin,
+            ray.at(hit_distance_from_ray_origin),
+            self,
+        ))
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let [a, b, c] = self.vertices();
+        match self.face.normals {
+            Some([na, nb, nc]) => triangle::interpolate_normal(
+                &triangle::barycentric(position, a, b, c),
+                &[
+                    self.mesh.normals[na],
+                    self.mesh.normals[nb],
+                    self.mesh.normals[nc],
+                ],
+            ),
+            None => (b - a).cross(&(c - a)).normalize(),
+        }
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        let [a, b, c] = self.vertices();
+        let weights = triangle::barycentric(position, a, b, c);
+        match self.face.texture_coords {
+            Some([ta, tb, tc]) => triangle::interpolate_texture_coords(
+                &weights,
+                &[
+                    self.mesh.texture_coords[ta],
+                    self.mesh.texture_coords[tb],
+                    self.mesh.texture_coords[tc],
+                ],
+            ),
+            None => Vector2::new(weights.y, weights.z),
+        }
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        let [a, b, c] = self.vertices();
+        let texture_coords = match self.face.texture_coords {
+            Some([ta, tb, tc]) => [
+                self.mesh.texture_coords[ta],
+                self.mesh.texture_coords[tb],
+                self.mesh.texture_coords[tc],
+            ],
+            None => triangle::default_texture_coords(),
+        };
+        triangle::tangent(&[*a, *b, *c], &texture_coords)
+            .unwrap_or_else(|| orthonormal_basis(&self.normal_at_position(position)).0)
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.mesh.materials[self.face.material].borrow()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        let [a, b, c] = self.vertices();
+        Some(triangle::bounding_box(&[*a, *b, *c]))
+    }
+}
+
+/// Indexed triangle mesh, every triangle shares the vertex buffers of the mesh
+/// and the mesh keeps its own hierarchy so it can be pushed in a `Scene` as a single shape.
+pub struct TriangleMesh {
+    triangles: Vec<MeshTriangle>,
+    bvh: Bvh,
+    bounds: Option<AABB>,
+    // indices of the emissive triangles and the total area up to each of them, to sample them as a light
+    emitters: Vec<usize>,
+    emitter_areas: Vec<f64>,
+    rng: RefCell<SmallRng>,
+}
+
+impl TriangleMesh {
+    /// `faces[i].material` indexes `materials`.
+    /// Fails if there are no faces or if a face indexes past the end of a buffer.
+    pub fn new(
+        positions: Vec<Vector3<f64>>,
+        normals: Vec<Vector3<f64>>,
+        texture_coords: Vec<Vector2<f64>>,
+        faces: Vec<MeshFace>,
+        materials: Vec<Rc<dyn Material>>,
+    ) -> Result<TriangleMesh, MeshError> {
+        if faces.is_empty() {
+            return Err(MeshError::Empty);
+        }
+        let is_valid = |face: &MeshFace| {
+            face.positions.iter().all(|&i| i < positions.len())
+                && face
+                    .normals
+                    .is_none_or(|indices| indices.iter().all(|&i| i < normals.len()))
+                && face
+                    .texture_coords
+                    .is_none_or(|indices| indices.iter().all(|&i| i < texture_coords.len()))
+                && face.material < materials.len()
+        };
+        if let Some(face) = faces.iter().position(|face| !is_valid(face)) {
+            return Err(MeshError::InvalidFace(face));
+        }
+        let mesh = Rc::new(MeshData {
+            positions,
+            normals,
+            texture_coords,
+            materials,
+        });
+        let triangles: Vec<MeshTriangle> = faces
+            .into_iter()
+            .map(|face| MeshTriangle {
+                mesh: Rc::clone(&mesh),
+                face,
+            })
+            .collect();
+        let bounding_boxes: Vec<_> = triangles.iter().map(|t| t.bounding_box()).collect();
+        let bounds = bounding_boxes
+            .iter()
+            .flatten()
+            .fold(None, |bounds: Option<AABB>, b| match bounds {
+                Some(bounds) => Some(bounds.surrounding(b)),
+                None => Some(*b),
+            });
+        let emitters: Vec<usize> = (0..triangles.len())
+            .filter(|&index| triangles[index].is_emissive())
+            .collect();
+        let emitter_areas = emitters
+            .iter()
+            .scan(0.0, |area, &index| {
+                *area += triangles[index].area();
+                Some(*area)
+            })
+            .collect();
+        Ok(TriangleMesh {
+            bvh: Bvh::new(&bounding_boxes),
+            triangles,
+            bounds,
+            emitters,
+            emitter_areas,
+            rng: RefCell::new(SmallRng::from_entropy()),
+        })
+    }
+
+    pub fn triangles(&self) -> &[MeshTriangle] {
+        &self.triangles
+    }
+
+    /// Triangle the closest to `position`, collisions already reference the touched triangle
+    /// so this is only needed when the mesh itself is asked about a position.
+    fn triangle_at(&self, position: &Vector3<f64>) -> &MeshTriangle {
+        let mut closest: Option<(&MeshTriangle, f64)> = None;
+        self.bvh
+            .visit_containing(position, POSITION_MARGIN, |index| {
+                let triangle = &self.triangles[index];
+                let distance = triangle.distance_to(position);
+                if closest.is_none_or(|(_, closest_distance)| distance < closest_distance) {
+                    closest = Some((triangle, distance));
+                }
+            });
+        // Positions away from the surface have no answer, any triangle will do.
+        closest.map_or(&self.triangles[0], |(triangle, _)| triangle)
+    }
+}
+
+impl Shape for TriangleMesh {
+    fn collide(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision<'_>> {
+        let triangles = &self.triangles;
+        self.bvh
+            .find_collision(ray, t_min, t_max, |index, t_min, t_max| {
+                triangles[index].collide(ray, t_min, t_max)
+            })
+            .map(|(collision, _)| collision)
+    }
+
+    fn normal_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        self.triangle_at(position).normal_at_position(position)
+    }
+
+    fn texture_coords_at_position(&self, position: &Vector3<f64>) -> Vector2<f64> {
+        self.triangle_at(position)
+            .texture_coords_at_position(position)
+    }
+
+    fn tangent_at_position(&self, position: &Vector3<f64>) -> Vector3<f64> {
+        self.triangle_at(position).tangent_at_position(position)
+    }
+
+    /// Collisions reference the touched triangle, which answers with its own material,
+    /// this is only the one of the first triangle.
+    fn material(&self) -> &dyn Material {
+        self.triangles[0].material()
+    }
+
+    fn is_emissive(&self) -> bool {
+        !self.emitters.is_empty()
+    }
+
+    fn bounding_box(&self) -> Option<AABB> {
+        self.bounds
+    }
+
+    /// Uniform on the area of the emissive triangles, converted to solid angle.
+    fn pdf_value(&self, origin: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        let total_area = match self.emitter_areas.last() {
+            Some(&area) if area > 0.0 => area,
+            _ => return 0.0,
+        };
+        let ray = Ray::new(*origin, *direction);
+        let triangles = &self.triangles;
+        let (collision, index) =
+            match self
+                .bvh
+                .find_collision(&ray, T_MIN, f64::INFINITY, |index, t_min, t_max| {
+                    triangles[index].collide(&ray, t_min, t_max)
+                }) {
+                Some(hit) => hit,
+                None => return 0.0,
+            };
+        if !triangles[index].is_emissive() {
+            return 0.0;
+        }
+        let distance_squared = collision.dist_from_origin().powi(2) * direction.magnitude_squared();
+        let cosine = (direction.dot(&collision.geometric_normal()) / direction.magnitude()).abs();
+        distance_squared / (cosine * total_area)
+    }
+
+    /// Picks an emissive triangle in proportion to its area and a uniform point on it.
+    fn random_direction(&self, origin: &Vector3<f64>) -> Option<Vector3<f64>> {
+        let total_area = *self.emitter_areas.last()?;
+        let mut rng = self.rng.borrow_mut();
+        let area = rng.gen_range(0.0, total_area);
+        let emitter = self
+            .emitter_areas
+            .iter()
+            .position(|&up_to| area < up_to)
+            .unwrap_or(self.emitters.len() - 1);
+        let [a, b, c] = self.triangles[self.emitters[emitter]].vertices();
+        let s = rng.gen_range(0.0_f64, 1.0).sqrt();
+        let t = rng.gen_range(0.0, 1.0);
+        let point = a * (1.0 - s) + b * (s * (1.0 - t)) + c * (s * t);
+        Some(point - origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::materials::lambertian_diffuse::Lambertian;
+
+    /// Unit square in the plane z = 0 made of a diffuse and an emissive triangle.
+    fn square(faces: Vec<MeshFace>) -> Result<TriangleMesh, MeshError> {
+        TriangleMesh::new(
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            ],
+            vec![],
+            vec![],
+            faces,
+            vec![
+                Rc::new(Lambertian::new(0.5)),
+                Rc::new(DiffuseLight::new(Vector3::repeat(1.0), 1.0)),
+            ],
+        )
+    }
+
+    fn face(positions: [usize; 3], material: usize) -> MeshFace {
+        MeshFace {
+            positions,
+            normals: None,
+            texture_coords: None,
+            material,
+        }
+    }
+
+    #[test]
+    fn rejects_faces_indexing_past_the_buffers() {
+        let error = |faces| square(faces).err().map(|e| e.to_string());
+        assert_eq!(error(vec![]), Some(MeshError::Empty.to_string()));
+        assert!(matches!(
+            square(vec![face([0, 1, 2], 0), face([0, 2, 4], 0)]),
+            Err(MeshError::InvalidFace(1))
+        ));
+        assert!(matches!(
+            square(vec![face([0, 1, 2], 2)]),
+            Err(MeshError::InvalidFace(0))
+        ));
+        let mut with_normals = face([0, 1, 2], 0);
+        with_normals.normals = Some([0, 0, 0]);
+        assert!(matches!(
+            square(vec![with_normals]),
+            Err(MeshError::InvalidFace(0))
+        ));
+    }
+
+    #[test]
+    fn collisions_have_the_material_of_the_touched_triangle() {
+        let mesh = square(vec![face([0, 1, 2], 0), face([0, 2, 3], 1)]).unwrap();
+        assert!(mesh.is_emissive());
+        let direction = Vector3::new(0.0, 0.0, -1.0);
+        let diffuse = Ray::new(Vector3::new(0.75, 0.25, 1.0), direction);
+        let emissive = Ray::new(Vector3::new(0.25, 0.75, 1.0), direction);
+        assert_eq!(
+            mesh.collide(&diffuse, 0.0, 10.0).unwrap().emitted(&diffuse),
+            Vector3::zeros()
+        );
+        assert_eq!(
+            mesh.collide(&emissive, 0.0, 10.0)
+                .unwrap()
+                .emitted(&emissive),
+            Vector3::repeat(1.0)
+        );
+        assert!(!square(vec![face([0, 1, 2], 0)]).unwrap().is_emissive());
+    }
+
+    #[test]
+    fn samples_only_emissive_triangles() {
+        let mesh = square(vec![face([0, 1, 2], 0), face([0, 2, 3], 1)]).unwrap();
+        let origin = Vector3::new(0.5, 0.5, 1.0);
+        for _ in 0..100 {
+            let direction = mesh.random_direction(&origin).unwrap();
+            let point = origin + direction;
+            assert!(point.y >= point.x - 1e-9, "{:?}", point);
+            assert!(mesh.pdf_value(&origin, &direction) > 0.0);
+        }
+        let toward_diffuse = Vector3::new(0.25, -0.25, -1.0);
+        assert_eq!(mesh.pdf_value(&origin, &toward_diffuse), 0.0);
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let mesh = square(vec![face([0, 1, 2], 1), face([0, 2, 3], 1)]).unwrap();
+        let origin = Vector3::new(0.3, 0.6, 0.5);
+        // Monte Carlo integral of the pdf over the hemisphere below the origin.
+        let mut rng = SmallRng::seed_from_u64(0);
+        let count = 200_000;
+        let sum: f64 = (0..count)
+            .map(|_| {
+                let z: f64 = rng.gen_range(-1.0, 0.0);
+                let phi = rng.gen_range(0.0, std::f64::consts::TAU);
+                let r = (1.0 - z * z).sqrt();
+                let direction = Vector3::new(r * phi.cos(), r * phi.sin(), z);
+                mesh.pdf_value(&origin, &direction)
+            })
+            .sum();
+        let integral = sum / count as f64 * std::f64::consts::TAU;
+        assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+    }
+}
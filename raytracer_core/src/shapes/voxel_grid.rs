@@ -0,0 +1,98 @@
+use nalgebra::Vector3;
+
+use crate::shapes::aabb::AABB;
+
+/// Scalar values sampled on a regular 3D grid filling a box, like the density of a volume.
+pub struct VoxelGrid {
+    resolution: [usize; 3],
+    bounds: AABB,
+    // x varies first, then y, then z
+    values: Vec<f64>,
+    max_value: f64,
+}
+
+impl VoxelGrid {
+    /// `values` holds `resolution[0] * resolution[1] * resolution[2]` voxels, x varying first, then y, then z.
+    /// They must be finite and non negative.
+    pub fn new(resolution: [usize; 3], bounds: AABB, values: Vec<f64>) -> VoxelGrid {
+        assert_eq!(
+            values.len(),
+            resolution.iter().product::<usize>(),
+            "the number of voxels doesn't match the resolution"
+        );
+        assert!(
+            values
+                .iter()
+                .all(|&value| value.is_finite() && value >= 0.0),
+            "voxel values must be finite and non negative"
+        );
+        let max_value = values.iter().cloned().fold(0.0, f64::max);
+        VoxelGrid {
+            resolution,
+            bounds,
+            values,
+            max_value,
+        }
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    pub fn bounds(&self) -> &AABB {
+        &self.bounds
+    }
+
+    /// Largest value of the grid, no interpolated value is above it.
+    pub fn max_value(&self) -> f64 {
+        self.max_value
+    }
+
+    /// Value at `position` interpolated between the centers of the 8 closest voxels, 0 outside of the box.
+    pub fn value_at(&self, position: &Vector3<f64>) -> f64 {
+        if !self.bounds.contains(position) {
+            return 0.0;
+        }
+        let relative = (position - self.bounds.min()).component_div(&self.bounds.extent());
+        let mut cell = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let coordinate = relative[axis] * self.resolution[axis] as f64 - 0.5;
+            let floor = coordinate.floor();
+            cell[axis] = floor as i64;
+            fraction[axis] = coordinate - floor;
+        }
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let weight: f64 = (0..3)
+                .map(|axis| {
+                    if offset[axis] == 1 {
+                        fraction[axis]
+                    } else {
+                        1.0 - fraction[axis]
+                    }
+                })
+                .product();
+            if weight > 0.0 {
+                value += weight
+                    * self.voxel(
+                        cell[0] + offset[0],
+                        cell[1] + offset[1],
+                        cell[2] + offset[2],
+                    );
+            }
+        }
+        value
+    }
+
+    /// Value of a voxel, the ones of the faces are extended outside.
+    fn voxel(&self, x: i64, y: i64, z: i64) -> f64 {
+        let [width, height, depth] = self.resolution;
+        let x = x.clamp(0, width as i64 - 1) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        let z = z.clamp(0, depth as i64 - 1) as usize;
+        self.values[(z * height + y) * width + x]
+    }
+}
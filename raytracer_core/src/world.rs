@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+
+use nalgebra::Vector3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::backgrounds::background::Background;
+use crate::lights::light::Light;
+use crate::shapes::aabb::AABB;
+use crate::shapes::bvh::Bvh;
+use crate::shapes::collision::Collision;
+use crate::shapes::constant_medium::ConstantMedium;
+use crate::shapes::ray::Ray;
+use crate::shapes::shape::Shape;
+
+pub(crate) const T_MIN: f64 = 0.001;
+pub(crate) const T_MAX: f64 = 100_000.0;
+
+/// Acceleration structure built from a `Scene`, kept around while the bounds and emissive shapes
+/// of the scene stay the same.
+pub(crate) struct SceneCache {
+    bounding_boxes: Vec<Option<AABB>>,
+    bvh: Bvh,
+    emitters: Vec<usize>,
+    rng: RefCell<SmallRng>,
+}
+
+impl SceneCache {
+    pub(crate) fn new(scene: &[Box<dyn Shape>]) -> SceneCache {
+        let bounding_boxes: Vec<_> = scene.iter().map(|shape| shape.bounding_box()).collect();
+        SceneCache {
+            bvh: Bvh::new(&bounding_boxes),
+            bounding_boxes,
+            emitters: emitters(scene).collect(),
+            rng: RefCell::new(SmallRng::from_entropy()),
+        }
+    }
+
+    /// The hierarchy only depends on the bounds of the shapes and the emitters on their materials,
+    /// so shapes replaced or modified in place are noticed without the caller telling.
+    pub(crate) fn is_built_from(&self, scene: &[Box<dyn Shape>]) -> bool {
+        self.bounding_boxes.len() == scene.len()
+            && scene
+                .iter()
+                .zip(&self.bounding_boxes)
+                .all(|(shape, bounds)| shape.bounding_box() == *bounds)
+            && emitters(scene).eq(self.emitters.iter().copied())
+    }
+
+    /// Returns the cache for `scene`, rebuilding it if the scene changed since it was built.
+    pub(crate) fn update<'a>(
+        cache: &'a mut Option<SceneCache>,
+        scene: &[Box<dyn Shape>],
+    ) -> &'a SceneCache {
+        match cache {
+            Some(c) if c.is_built_from(scene) => {}
+            _ => *cache = Some(SceneCache::new(scene)),
+        }
+        cache.as_ref().unwrap()
+    }
+}
+
+/// Indices of the emissive shapes of the scene.
+fn emitters(scene: &[Box<dyn Shape>]) -> impl Iterator<Item = usize> + '_ {
+    (0..scene.len()).filter(move |&index| scene[index].is_emissive())
+}
+
+/// Everything a ray needs to know about the scene it's traced in.
+pub struct World<'a> {
+    shapes: &'a [Box<dyn Shape>],
+    bvh: &'a Bvh,
+    emitters: &'a [usize],
+    lights: &'a [Box<dyn Light>],
+    background: &'a dyn Background,
+    fog: Option<&'a ConstantMedium>,
+    rng: &'a RefCell<SmallRng>,
+}
+
+impl<'a> World<'a> {
+    pub(crate) fn new(
+        shapes: &'a [Box<dyn Shape>],
+        cache: &'a SceneCache,
+        lights: &'a [Box<dyn Light>],
+        background: &'a dyn Background,
+        fog: Option<&'a ConstantMedium>,
+    ) -> World<'a> {
+        World {
+            shapes,
+            bvh: &cache.bvh,
+            emitters: &cache.emitters,
+            lights,
+            background,
+            fog,
+            rng: &cache.rng,
+        }
+    }
+
+    pub fn background(&self) -> &'a dyn Background {
+        self.background
+    }
+
+    pub fn shapes(&self) -> &'a [Box<dyn Shape>] {
+        self.shapes
+    }
+
+    /// Indices of the emissive shapes of the scene.
+    pub fn emitters(&self) -> &'a [usize] {
+        self.emitters
+    }
+
+    pub fn lights(&self) -> &'a [Box<dyn Light>] {
+        self.lights
+    }
+
+    /// Fraction of the light going through the scene between the ray origin and `distance` along the ray,
+    /// 0 if an opaque shape is in the way. The ray direction must be a unit vector.
+    pub fn transmittance(&self, ray: &Ray, distance: f64) -> f64 {
+        let shapes = self.shapes;
+        let t_max = distance.min(T_MAX);
+        let mut transmittance = 1.0;
+        let is_blocked = self
+            .bvh
+            .find_collision(ray, T_MIN, t_max, |index, t_min, t_max| {
+                match shapes[index].transmittance(ray, t_min, t_max) {
+                    Some(shape_transmittance) => {
+                        // Keep looking for what's behind.
+                        transmittance *= shape_transmittance;
+                        None
+                    }
+                    None => shapes[index].collide(ray, t_min, t_max),
+                }
+            })
+            .is_some();
+        if is_blocked {
+            return 0.0;
+        }
+        match self.fog {
+            Some(fog) if distance.is_finite() => {
+                transmittance * fog.transmittance(ray, T_MIN, t_max).unwrap_or(1.0)
+            }
+            _ => transmittance,
+        }
+    }
+
+    /// Scattering in the fog before `t_max`. The fog stops at the last surface,
+    /// rays escaping toward the background or a light at infinity aren't affected.
+    fn fog_collision(&self, ray: &Ray, t_max: f64) -> Option<Collision<'a>> {
+        self.fog?.collide(ray, T_MIN, t_max)
+    }
+
+    /// Picks an emissive shape and a direction toward it from `origin`,
+    /// returns the index of the shape, the unit direction and its probability density.
+    pub(crate) fn sample_emitter(
+        &self,
+        origin: &Vector3<f64>,
+    ) -> Option<(usize, Vector3<f64>, f64)> {
+        if self.emitters.is_empty() {
+            return None;
+        }
+        let index = self.emitters[self.rng.borrow_mut().gen_range(0, self.emitters.len())];
+        let direction = self.shapes[index].random_direction(origin)?.normalize();
+        let pdf = self.emitter_pdf(index, origin, &direction);
+        if pdf > 0.0 {
+            Some((index, direction, pdf))
+        } else {
+            None
+        }
+    }
+
+    /// Probability density of `sample_emitter` choosing `direction` toward the shape at `index`.
+    pub(crate) fn emitter_pdf(
+        &self,
+        index: usize,
+        origin: &Vector3<f64>,
+        direction: &Vector3<f64>,
+    ) -> f64 {
+        if !self.emitters.contains(&index) {
+            return 0.0;
+        }
+        self.shapes[index].pdf_value(origin, direction) / self.emitters.len() as f64
+    }
+
+    /// Returns the nearest collision and the index of the touched shape in the scene,
+    /// `shapes().len()` for a scattering in the fog.
+    pub fn find_collision(&self, ray: &Ray) -> Option<(Collision<'a>, usize)> {
+        let shapes = self.shapes;
+        let (collision, index) =
+            self.bvh
+                .find_collision(ray, T_MIN, T_MAX, |index, t_min, t_max| {
+                    shapes[index].collide(ray, t_min, t_max)
+                })?;
+        let (collision, index) = match self.fog_collision(ray, collision.dist_from_origin()) {
+            Some(fog_collision) => (fog_collision, shapes.len()),
+            None => (collision, index),
+        };
+        if ray.spread() <= 0.0 {
+            return Some((collision, index));
+        }
+        let distance = (collision.position() - ray.origin()).magnitude();
+        // The footprint stretches on surfaces seen at grazing angles.
+        let cos = ray
+            .direction()
+            .normalize()
+            .dot(&collision.geometric_normal())
+            .abs();
+        let footprint = ray.spread() * distance / cos.max(0.1);
+        Some((collision.with_footprint(footprint), index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::gradient::Gradient;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::materials::lambertian_diffuse::Lambertian;
+    use crate::shapes::sphere::Sphere;
+
+    fn sphere(x: f64) -> Box<dyn Shape> {
+        Box::new(Sphere::new(
+            Vector3::new(x, 0.0, 0.0),
+            1.0,
+            Box::new(Lambertian::new(0.5)),
+        ))
+    }
+
+    #[test]
+    fn cache_notices_replaced_shapes() {
+        let mut scene = vec![sphere(0.0), sphere(3.0)];
+        let cache = SceneCache::new(&scene);
+        assert!(cache.is_built_from(&scene));
+        assert!(cache.is_built_from(&[sphere(0.0), sphere(3.0)]));
+
+        // Same length, moved shape.
+        scene[1] = sphere(6.0);
+        assert!(!cache.is_built_from(&scene));
+        let cache = SceneCache::new(&scene);
+        let ray = Ray::new(Vector3::new(6.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let background = Gradient::default();
+        let world = World::new(&scene, &cache, &[], &background, None);
+        assert_eq!(world.find_collision(&ray).map(|(_, index)| index), Some(1));
+
+        // Same bounds, new light.
+        scene[0] = Box::new(Sphere::new(
+            Vector3::zeros(),
+            1.0,
+            Box::new(DiffuseLight::new(Vector3::repeat(1.0), 1.0)),
+        ));
+        assert!(!cache.is_built_from(&scene));
+        assert_eq!(SceneCache::new(&scene).emitters, vec![0]);
+    }
+}
@@ -10,6 +10,7 @@ use rand::SeedableRng;
 use raytracer_core::materials::dielectric::Dielectric;
 use raytracer_core::materials::lambertian_diffuse::Lambertian;
 use raytracer_core::materials::metal::Metal;
+use raytracer_core::shapes::plane::Plane;
 use raytracer_core::shapes::sphere::Sphere;
 use raytracer_core::Vector3;
 use raytracer_core::{
@@ -68,9 +69,9 @@ fn main_loop() {
             0.5,
             Box::new(Dielectric::new(Vector3::new(1.0, 0.8, 0.80), 1.05)),
         );
-        let sphere2 = Sphere::new(
-            Vector3::new(0.0, -100.5, -1.0),
-            100.0,
+        let ground = Plane::new(
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
             Box::new(Lambertian::new_from_hex(0x007070)),
         );
         let sphere3 = Sphere::new(
@@ -81,12 +82,12 @@ fn main_loop() {
         let sphere4 = Sphere::new(
             Vector3::new(-0.0, 0.0, -1.0),
             0.5,
-            Box::new(
+            Box::new(Lambertian::new(
                 Texture::load_from_file(Path::new("textures/bergsjostolen.jpg"), 1.0).unwrap(),
-            ),
+            )),
         );
 
-        let scene: Scene = vec![Box::new(sphere), Box::new(sphere2), Box::new(sphere3), Box::new(sphere4)];
+        let scene: Scene = vec![Box::new(sphere), Box::new(ground), Box::new(sphere3), Box::new(sphere4)];
         let mut spp = 1;
         let rng = SmallRng::from_entropy();
         let mut raytracer = Raytracer::new(width, height, rng);
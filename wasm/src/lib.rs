@@ -4,6 +4,7 @@ use wasm_bindgen::prelude::*;
 
 use rand::prelude::*;
 use raytracer_core::materials::metal::Metal;
+use raytracer_core::shapes::plane::Plane;
 use raytracer_core::shapes::sphere::Sphere;
 use raytracer_core::Vector3;
 use raytracer_core::{
@@ -92,9 +93,9 @@ pub fn render() {
         0.5,
         Box::new(Metal::new(Vector3::new(0.8, 0.8, 0.8), 0.0)),
     );
-    let sphere2 = Sphere::new(
-        Vector3::new(0.0, -100.5, -1.0),
-        100.0,
+    let ground = Plane::new(
+        Vector3::new(0.0, -0.5, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
         Box::new(Metal::new(Vector3::new(0.8, 0.8, 0.8), 0.0)),
     );
     let sphere3 = Sphere::new(
@@ -102,7 +103,7 @@ pub fn render() {
         0.1,
         Box::new(Metal::new(Vector3::new(0.8, 0.8, 0.8), 0.0)),
     );
-    let scene: Scene = vec![Box::new(sphere), Box::new(sphere2), Box::new(sphere3)];
+    let scene: Scene = vec![Box::new(sphere), Box::new(ground), Box::new(sphere3)];
     let rng = rand::rngs::StdRng::seed_from_u64(0);
     let mut communicator = RendererCommunicator {};
     let mut raytracer = Raytracer::new(WIDTH as f64, HEIGHT as f64, rng);